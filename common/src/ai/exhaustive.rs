@@ -1,75 +1,300 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
-use crate::{BoardItem, BoardOutcome, BoardState, Game, MarkTileResult, Player};
+use crate::{BoardState, Game, MarkTileResult, Play, Player, TimeSource};
+
+use super::{Agent, Evaluation, SearchInfo, SearchLimits};
 
 pub fn make_move(game: &mut Game) {
     if !matches!(game.state, BoardState::InProgress) {
         return;
     }
 
-    let region_indices = if let Some(index) = game.allowed_region_index() {
-        vec![(index, &game.board[index])]
-    } else {
-        game.board
-            .enumerate()
-            .filter(|(_, region)| region.is_markable())
-            .collect()
-    };
-    let mut all_moves = Vec::new();
-    let mut cache = HashMap::new();
-    for (region_index, region) in region_indices {
-        for (tile_index, _) in region
-            .board
-            .enumerate()
-            .filter(|(_, tile)| tile.is_markable())
-        {
-            let mut game_clone = game.clone();
-            game_clone.mark_tile((region_index, tile_index));
-            let score = simulate(&game_clone, &mut cache);
-            all_moves.push((score, (region_index, tile_index)));
+    let play = Search::new(None, |_| false)
+        .best_play(game)
+        .expect("a search that never stops should always find a play.");
+    assert!(!matches!(game.mark_tile(play), MarkTileResult::NoChange));
+}
+
+/// An [`Agent`] that scores every possible game from the current one, and makes the play with the
+/// highest score.
+///
+/// The search deepens iteratively: it scores the games up to one play ahead, counting those that
+/// aren't over by then as draws, then up to two plays ahead, and so on until it reaches the end of
+/// every game or a limit. The play found by the deepest finished search is made, and the first
+/// search always finishes so that there is one. Without limits, only that first search runs, like
+/// a single iteration of other agents. To search to the end, which is only practical near the end
+/// of a game, use [`SearchLimits::iterations`] with [`usize::MAX`].
+///
+/// The iteration limit counts the positions scored by all the searches, and the time limit is
+/// measured with the given [`TimeSource`], which is [`Instant`] by default outside of the
+/// browser.
+#[derive(Debug, Clone)]
+pub struct ExhaustiveAgent<S = Instant> {
+    time_source: S,
+    info: Option<SearchInfo>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ExhaustiveAgent {
+    /// Returns an agent measuring time with [`Instant`].
+    pub fn new() -> Self {
+        Self::with_time_source(Instant::now())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ExhaustiveAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: TimeSource> ExhaustiveAgent<S> {
+    /// Returns an agent measuring time with the given time source.
+    pub fn with_time_source(time_source: S) -> Self {
+        Self {
+            time_source,
+            info: None,
         }
     }
-    let (_, play) = all_moves
-        .into_iter()
-        .max_by_key(|&(score, _)| score)
-        .expect("should have at least one possible move.");
-    assert!(!matches!(game.mark_tile(play), MarkTileResult::NoChange));
 }
 
-fn simulate(game: &Game, cache: &mut HashMap<Game, usize>) -> usize {
-    assert!(matches!(game.state, BoardState::InProgress));
-
-    if let Some(&score) = cache.get(game) {
-        return score;
-    }
-
-    let mut score = 0;
-    for (region_index, region) in game
-        .board
-        .enumerate()
-        .filter(|(index, region)| game.is_region_enabled(*index) && region.is_markable())
-    {
-        for (tile_index, _) in region
-            .board
-            .enumerate()
-            .filter(|(_, tile)| tile.is_markable())
-        {
-            let mut game_clone = game.clone();
-            match game_clone.mark_tile((region_index, tile_index)) {
-                MarkTileResult::NoChange => panic!("only markable indices should be used and this should never results in NoChange."),
-                MarkTileResult::TileMarked => {
-                    let sub_score = simulate(&game_clone, cache);
-                    cache.insert(game_clone, sub_score);
-                    score += sub_score;
-                },
-                MarkTileResult::OutcomeDecided(outcome) => match outcome {
-                    BoardOutcome::Draw => (),
-                    BoardOutcome::WonBy(Player::Circle) => score -= 1,
-                    BoardOutcome::WonBy(Player::Cross) => score += 1,
-                }
+impl<S: TimeSource> Agent for ExhaustiveAgent<S> {
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Play {
+        assert!(matches!(game.state, BoardState::InProgress));
+
+        let mut game = game.clone();
+        let start = self.time_source.now();
+        let elapsed = || self.time_source.now().saturating_sub(start);
+        let mut best_play = None;
+        let mut iterations = 0;
+        for max_depth in 1.. {
+            let (is_first, previous_iterations) = (best_play.is_none(), iterations);
+            let mut search = Search::new(Some(max_depth), |n_scored| {
+                !is_first && limits.is_reached(previous_iterations + n_scored, elapsed())
+            });
+            let play = search.best_play(&mut game);
+            iterations += search.cache.scores.len();
+            let Some(play) = play else {
+                break;
+            };
+            best_play = Some(play);
+            if !search.reached_max_depth || limits.is_reached(iterations, elapsed()) {
+                break;
             }
         }
+        let play = best_play.expect("the first search should always finish.");
+        self.info = Some(SearchInfo {
+            iterations,
+            plays: Vec::new(),
+            seed: None,
+        });
+        play
     }
 
-    score
+    fn search_info(&self) -> Option<&SearchInfo> {
+        self.info.as_ref()
+    }
+}
+
+/// A search scoring the possible games from a game, up to a maximum depth.
+struct Search<F> {
+    cache: Cache,
+    /// The number of plays ahead up to which games are scored, or `None` to score them to the end.
+    max_depth: Option<usize>,
+    /// Whether some game wasn't over at the maximum depth, so a deeper search could score more.
+    reached_max_depth: bool,
+    /// Returns whether the search should stop, given the number of positions it has scored.
+    should_stop: F,
+}
+
+impl<F: FnMut(usize) -> bool> Search<F> {
+    fn new(max_depth: Option<usize>, should_stop: F) -> Self {
+        Self {
+            cache: Cache::default(),
+            max_depth,
+            reached_max_depth: false,
+            should_stop,
+        }
+    }
+
+    /// Returns the play with the highest total score from the given in-progress game for the
+    /// current player, or `None` if the search was stopped.
+    ///
+    /// The game is left as it was.
+    fn best_play(&mut self, game: &mut Game) -> Option<Play> {
+        let player = game.current_player;
+        let previous_play_index = game.previous_play_index;
+        let mut all_moves = Vec::new();
+        for play in game.legal_moves() {
+            let score = self.score_play(game, play, player, 1);
+            game.unmark_tile(play, previous_play_index);
+            all_moves.push((score?, play));
+        }
+        let (_, play) = all_moves
+            .into_iter()
+            .max_by_key(|&(score, _)| score)
+            .expect("should have at least one possible move.");
+        Some(play)
+    }
+
+    /// Returns the total score of all the possible games from the given in-progress game state,
+    /// reached in the given number of plays, for the given player, where each win counts as 1 and
+    /// each loss as -1. Returns `None` if the search was stopped.
+    ///
+    /// Scores are cached by the canonical form of the game, since symmetric games have the same
+    /// score. A game is always reached in the same number of plays, which is its number of marks,
+    /// so cached scores are scored up to the same depth.
+    fn simulate(&mut self, game: &mut Game, player: Player, depth: usize) -> Option<i64> {
+        assert!(matches!(game.state, BoardState::InProgress));
+
+        if self.max_depth == Some(depth) {
+            self.reached_max_depth = true;
+            return Some(0);
+        }
+        let (canonical, _) = game.canonical();
+        if let Some(score) = self.cache.get(&canonical) {
+            return Some(score);
+        }
+        if (self.should_stop)(self.cache.scores.len()) {
+            return None;
+        }
+
+        let previous_play_index = game.previous_play_index;
+        let mut score = 0;
+        for play in game.legal_moves() {
+            let play_score = self.score_play(game, play, player, depth + 1);
+            game.unmark_tile(play, previous_play_index);
+            score += play_score?;
+        }
+
+        self.cache.insert(canonical, score);
+        Some(score)
+    }
+
+    /// Makes the given play, which is the given number of plays ahead, and returns the total
+    /// score of all the possible games from the resulting game state, leaving the play made for
+    /// the caller to revert. Returns `None` if the search was stopped.
+    fn score_play(
+        &mut self,
+        game: &mut Game,
+        play: Play,
+        player: Player,
+        depth: usize,
+    ) -> Option<i64> {
+        match game.mark_tile(play) {
+            MarkTileResult::NoChange => panic!(
+                "only markable indices should be used and this should never results in NoChange."
+            ),
+            MarkTileResult::TileMarked => self.simulate(game, player, depth),
+            MarkTileResult::OutcomeDecided(outcome) => {
+                Some(match Evaluation::of(outcome, player) {
+                    Evaluation::Win => 1,
+                    Evaluation::Draw => 0,
+                    Evaluation::Loss => -1,
+                })
+            }
+        }
+    }
+}
+
+/// Scores of games keyed by their [`Game::key`].
+///
+/// In debug builds, the game each key was stored for is kept too, to detect key collisions.
+#[derive(Debug, Default)]
+struct Cache {
+    scores: HashMap<u64, i64>,
+    #[cfg(debug_assertions)]
+    games: HashMap<u64, Game>,
+}
+
+impl Cache {
+    fn get(&self, game: &Game) -> Option<i64> {
+        let score = self.scores.get(&game.key()).copied();
+        #[cfg(debug_assertions)]
+        if score.is_some() {
+            assert_eq!(
+                self.games[&game.key()],
+                *game,
+                "different games should never have the same key."
+            );
+        }
+        score
+    }
+
+    fn insert(&mut self, game: Game, score: i64) {
+        self.scores.insert(game.key(), score);
+        #[cfg(debug_assertions)]
+        self.games.insert(game.key(), game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, time::Duration};
+
+    use super::*;
+    use crate::{testing::random_positions, Tile};
+
+    #[test]
+    fn deepening_matches_full_search() {
+        // Positions with at most 8 tiles left to play in, which can be searched to the end.
+        let n_open_tiles = |game: &Game| {
+            game.board
+                .tiles
+                .iter()
+                .filter(|region| game.rules.is_region_open(region))
+                .flat_map(|region| region.board.tiles)
+                .filter(|&tile| tile == Tile::Unmarked)
+                .count()
+        };
+        for seed in 0..5 {
+            let Some(game) = random_positions(Default::default(), seed)
+                .into_iter()
+                .find(|game| game.state == BoardState::InProgress && n_open_tiles(game) <= 8)
+            else {
+                continue;
+            };
+            let full = Search::new(None, |_| false).best_play(&mut game.clone());
+            let mut deepening = ExhaustiveAgent::new();
+            assert_eq!(
+                Some(deepening.choose_move(&game, &SearchLimits::iterations(usize::MAX))),
+                full,
+                "{game}"
+            );
+        }
+    }
+
+    #[test]
+    fn no_limits() {
+        // Without limits, only the plays one play ahead are scored.
+        let game = Game::new();
+        let mut agent = ExhaustiveAgent::new();
+        let play = agent.choose_move(&game, &SearchLimits::default());
+        assert!(game.legal_moves().any(|legal_play| legal_play == play));
+        assert!(agent.search_info().unwrap().iterations <= game.legal_moves().count());
+    }
+
+    #[test]
+    fn iteration_limit() {
+        let game = Game::new();
+        let mut agent = ExhaustiveAgent::new();
+        let play = agent.choose_move(&game, &SearchLimits::iterations(1000));
+        assert!(game.legal_moves().any(|legal_play| legal_play == play));
+        assert!(agent.search_info().unwrap().iterations <= 1000);
+    }
+
+    #[test]
+    fn time_limit() {
+        // Every reading of the time source takes a millisecond.
+        let time = Cell::new(Duration::ZERO);
+        let mut agent = ExhaustiveAgent::with_time_source(|| {
+            time.set(time.get() + Duration::from_millis(1));
+            time.get()
+        });
+        let game = Game::new();
+        let play = agent.choose_move(&game, &SearchLimits::time(Duration::from_secs(1)));
+        assert!(game.legal_moves().any(|legal_play| legal_play == play));
+        assert!(time.get() <= Duration::from_millis(1002));
+    }
 }
@@ -1,17 +1,15 @@
 use std::{
-    cell::{Ref, RefCell},
-    iter::Map,
-    ops::ControlFlow,
-    rc::{Rc, Weak},
-    slice,
+    fmt::{self, Debug},
+    ops::{ControlFlow, Range},
+    time::Instant,
 };
 
-use rand::{rngs::ThreadRng, seq::SliceRandom, thread_rng};
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng};
 use tracing::instrument;
 
-use crate::{BoardIndex, BoardOutcome, BoardState, Game, MarkTileResult, Play, Player, Region};
+use crate::{BoardOutcome, BoardState, Game, MarkTileResult, Play, Player, TimeSource};
 
-use super::random::GenerateMove;
+use super::{Agent, Evaluation, PlayInfo, Playable, SearchInfo, SearchLimits, SeedSequence};
 
 const EXPLORE_PARAM: f32 = std::f32::consts::SQRT_2;
 const SCORE_WIN: f32 = 1.0;
@@ -19,114 +17,594 @@ const SCORE_DRAW: f32 = 0.5;
 const SCORE_LOSS: f32 = 0.0;
 
 #[instrument(skip(should_terminate, game))]
-pub fn make_move(game: Game, should_terminate: impl Fn(&Node) -> bool) -> Play {
-    assert!(matches!(game.state, BoardState::InProgress));
+pub fn make_move<G: Playable>(
+    game: G,
+    should_terminate: impl Fn(&Node<'_, G::Play>) -> bool,
+) -> G::Play {
+    let mut searcher = MctsSearcher::new(game, thread_rng());
+    searcher.search(should_terminate);
+    searcher
+        .best_play()
+        .expect("an in-progress game should always have at least one possible play.")
+}
+
+/// Returns the best play in the given game found by searching on `n_threads` threads at once, with
+/// root parallelization, for `iterations` iterations in total.
+///
+/// Every thread grows a tree of its own from the game, and the total scores of the plays from the
+/// roots of all trees are added up to choose the play with the highest one. The iterations are
+/// split between the threads, the same as with [`MctsAgent::set_threads`].
+#[cfg(not(target_arch = "wasm32"))]
+#[instrument(skip(game))]
+pub fn make_move_parallel<G>(game: G, n_threads: usize, iterations: usize) -> G::Play
+where
+    G: Playable + Send,
+    G::Play: Send,
+{
+    assert!(
+        n_threads > 0,
+        "there should be at least one thread to search on."
+    );
+
+    let mut seeds = SeedSequence::random();
+    let mut searchers = (0..n_threads)
+        .map(|_| MctsSearcher::new(game.clone(), seeds.next_rng().1))
+        .collect::<Vec<_>>();
+    search_parallel(
+        &mut searchers,
+        || None,
+        |thread, root| root.n_visits() >= iteration_share(iterations, n_threads, thread),
+    );
+    best_merged_play(&merge_root_children(&searchers))
+}
+
+/// Returns the number of iterations the given thread runs when `iterations` are split between
+/// `n_threads` threads, as evenly as possible.
+#[cfg(not(target_arch = "wasm32"))]
+fn iteration_share(iterations: usize, n_threads: usize, thread: usize) -> usize {
+    iterations / n_threads + usize::from(thread < iterations % n_threads)
+}
 
-    let root = Node::new_root();
-    let mut cursor = Cursor::new(Rc::clone(&root), game);
+/// Runs the search of each of the given searchers on a thread of its own, until each thread's
+/// `should_terminate` returns `true` for the root node of its searcher, or the time returned by
+/// `remaining_time` on the calling thread runs out.
+///
+/// `should_terminate` gets the index of the searcher along with its root node. `remaining_time`
+/// returns `None` if the search isn't limited by time.
+#[cfg(not(target_arch = "wasm32"))]
+fn search_parallel<G, R>(
+    searchers: &mut [MctsSearcher<G, R>],
+    mut remaining_time: impl FnMut() -> Option<std::time::Duration>,
+    should_terminate: impl Fn(usize, &Node<'_, G::Play>) -> bool + Sync,
+) where
+    G: Playable + Send,
+    G::Play: Send,
+    R: Rng + Send,
+{
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::{self, RecvTimeoutError},
+        },
+        thread,
+        time::Duration,
+    };
+
+    let stop = AtomicBool::new(false);
+    // Nothing is sent on the channel: each thread drops its sender when its search is over, so
+    // receiving fails with `Disconnected` once all of them are.
+    let (finished_sender, finished) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        for (thread, searcher) in searchers.iter_mut().enumerate() {
+            let (stop, should_terminate) = (&stop, &should_terminate);
+            let finished_sender = finished_sender.clone();
+            scope.spawn(move || {
+                searcher
+                    .search(|root| stop.load(Ordering::Relaxed) || should_terminate(thread, root));
+                drop(finished_sender);
+            });
+        }
+        drop(finished_sender);
+
+        loop {
+            let received = match remaining_time() {
+                Some(Duration::ZERO) => {
+                    stop.store(true, Ordering::Relaxed);
+                    break;
+                }
+                Some(time) => finished.recv_timeout(time),
+                None => finished.recv().map_err(RecvTimeoutError::from),
+            };
+            if received == Err(RecvTimeoutError::Disconnected) {
+                break;
+            }
+        }
+        // The scope joins the threads, which stop at their next iteration.
+    });
+}
 
-    loop {
-        cursor.run();
-        if should_terminate(&root.borrow()) {
-            break;
+/// Returns every play from the root of the given searchers with the total score and number of
+/// visits of its nodes in all of them, in the order of the children of the first root.
+///
+/// The searchers must all have been searching the same game.
+fn merge_root_children<G: Playable, R: Rng>(
+    searchers: &[MctsSearcher<G, R>],
+) -> Vec<(G::Play, f32, usize)> {
+    let mut plays = Vec::<(G::Play, f32, usize)>::new();
+    for searcher in searchers {
+        for node in searcher.root().children() {
+            let play = node.play().expect(
+                "all nodes except the root should denote a play from the parent game state",
+            );
+            match plays.iter_mut().find(|(other, _, _)| *other == play) {
+                Some((_, score, n_visits)) => {
+                    *score += node.score();
+                    *n_visits += node.n_visits();
+                }
+                None => plays.push((play, node.score(), node.n_visits())),
+            }
         }
     }
+    plays
+}
 
-    let root = root.borrow();
-    let best_node = root
-        .children
+/// Returns the play with the highest total score from the result of [`merge_root_children`].
+fn best_merged_play<P: Copy>(plays: &[(P, f32, usize)]) -> P {
+    plays
         .iter()
-        .max_by(|a, b| a.borrow().score.total_cmp(&b.borrow().score))
-        .expect("an in-progress game should always have at least one possible play.");
-
-    let play = best_node
-        .borrow()
-        .play
-        .expect("all nodes except the root should denote a play from the parent game state");
-    play
+        .max_by(|(_, a, _), (_, b, _)| a.total_cmp(b))
+        .map(|&(play, _, _)| play)
+        .expect("an in-progress game should always have at least one possible play.")
 }
 
-type NodeRef = Rc<RefCell<Node>>;
+/// A Monte Carlo tree search that keeps its tree from one play to the next.
+///
+/// After a play is made with [`MctsSearcher::advance`], the subtree below that play becomes the
+/// new tree, so the iterations already spent on it aren't lost. Advancing by the plays of both
+/// players between searches lets each search continue where the previous one left off.
+#[derive(Debug)]
+pub struct MctsSearcher<G: Playable, R> {
+    /// The tree, whose root node represents `game`.
+    tree: Tree<G::Play>,
+    /// The game state at the root of the tree.
+    game: G,
+    /// RNG for the random elements in the MCTS algorithm.
+    rng: R,
+}
 
-/// A node in a Monte Carlo Tree
+impl<G: Playable, R: Rng> MctsSearcher<G, R> {
+    /// Returns a searcher with an empty tree for the given game.
+    pub fn new(game: G, rng: R) -> Self {
+        Self {
+            tree: Tree::new(),
+            game,
+            rng,
+        }
+    }
+
+    /// Returns the game state at the root of the tree.
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// Returns the root node of the tree.
+    pub fn root(&self) -> Node<'_, G::Play> {
+        self.tree.root()
+    }
+
+    /// Replaces the RNG used by further searches.
+    pub fn set_rng(&mut self, rng: R) {
+        self.rng = rng;
+    }
+
+    /// Runs iterations of the MCTS algorithm until `should_terminate` returns `true` for the root
+    /// node, growing the existing tree.
+    ///
+    /// The caller must ensure the game is still in progress. At least one iteration is run, and
+    /// the search is deterministic for a deterministic RNG, termination condition and tree.
+    pub fn search(&mut self, mut should_terminate: impl FnMut(&Node<'_, G::Play>) -> bool) {
+        assert!(matches!(self.game.state(), BoardState::InProgress));
+
+        let mut cursor = Cursor::new(&mut self.tree, &self.game, &mut self.rng);
+        loop {
+            cursor.run();
+            if should_terminate(&cursor.tree.root()) {
+                break;
+            }
+        }
+    }
+
+    /// Returns the play with the highest total score from the root, or `None` if the tree hasn't
+    /// been searched yet.
+    pub fn best_play(&self) -> Option<G::Play> {
+        if self.tree.node(ROOT).children.is_empty() {
+            return None;
+        }
+        let play = self.tree.node(self.tree.best_child(ROOT)).play;
+        Some(
+            play.expect(
+                "all nodes except the root should denote a play from the parent game state",
+            ),
+        )
+    }
+
+    /// Makes the given play in the game at the root, and keeps only the subtree below it.
+    ///
+    /// If the play is invalid, this returns [`MarkTileResult::NoChange`] and leaves the tree
+    /// untouched.
+    pub fn advance(&mut self, play: G::Play) -> MarkTileResult {
+        let result = self.game.mark_tile(play);
+        if matches!(result, MarkTileResult::NoChange) {
+            return result;
+        }
+
+        self.tree = match self.tree.find_child(ROOT, play) {
+            Some(child) => self.tree.subtree(child),
+            None => Tree::new(),
+        };
+        result
+    }
+
+    /// Advances the tree to the given game if it follows from the game at the root in at most two
+    /// plays, such as a play of each player, and returns whether it did.
+    ///
+    /// This is for callers that only know the current game and not the plays that led to it.
+    pub fn advance_to(&mut self, game: &G) -> bool
+    where
+        G: PartialEq,
+    {
+        if self.game == *game {
+            return true;
+        }
+        let plays = self.game.legal_moves().collect::<Vec<_>>();
+        for play in plays {
+            let mut next = self.game.clone();
+            next.mark_tile(play);
+            if next == *game {
+                self.advance(play);
+                return true;
+            }
+            if !matches!(next.state(), BoardState::InProgress) {
+                continue;
+            }
+            for reply in next.legal_moves() {
+                let mut next = next.clone();
+                next.mark_tile(reply);
+                if next == *game {
+                    self.advance(play);
+                    self.advance(reply);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// An [`Agent`] that searches with the MCTS algorithm, and makes the play with the highest total
+/// score.
 ///
-/// Each node (aside from the *root* node) stores a play that can be made in the game from its
-/// parent state.
-/// Each node represents a game state that can be reached by applying the play to its parent state
-/// starting from the root.
+/// The time limit of the search is measured with the given [`TimeSource`], which is
+/// [`Instant`] by default outside of the browser. Searches limited by time only aren't
+/// reproducible, since the number of iterations depends on the speed of the machine.
 ///
-/// A *terminal* node is a node that represents a game-over state.
+/// With [`MctsAgent::set_tree_reuse`], the agent keeps its tree between searches with an
+/// [`MctsSearcher`]. Searches then depend on the earlier ones too, so they can't be reproduced
+/// from their seed alone.
 ///
-/// A *leaf* node is a node with can be expanded (is not *terminal*) but hasn't been expanded yet,
-/// so it has no children.
+/// Outside of the browser, [`MctsAgent::set_threads`] makes the agent search on several threads
+/// with root parallelization. See [`make_move_parallel`].
+#[derive(Debug)]
+pub struct MctsAgent<S = Instant> {
+    time_source: S,
+    seeds: SeedSequence,
+    /// The number of threads to search on, each with a searcher of its own.
+    n_threads: usize,
+    /// The searchers of the last search, kept only if the tree is reused.
+    searchers: Vec<MctsSearcher<Game, StdRng>>,
+    reuse_tree: bool,
+    info: Option<SearchInfo>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MctsAgent {
+    /// Returns an agent measuring time with [`Instant`], with a random seed.
+    pub fn new() -> Self {
+        Self::with_time_source(Instant::now())
+    }
+
+    /// Returns an agent measuring time with [`Instant`], whose first search uses the given seed.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut agent = Self::new();
+        agent.set_seed(seed);
+        agent
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for MctsAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: TimeSource> MctsAgent<S> {
+    /// Returns an agent measuring time with the given time source, with a random seed.
+    pub fn with_time_source(time_source: S) -> Self {
+        Self {
+            time_source,
+            seeds: SeedSequence::random(),
+            n_threads: 1,
+            searchers: Vec::new(),
+            reuse_tree: false,
+            info: None,
+        }
+    }
+
+    /// Sets the seed of the next search.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seeds = SeedSequence::new(seed);
+    }
+
+    /// Sets whether the agent keeps its tree between searches, which is off by default.
+    ///
+    /// A kept tree is reused when the next game follows from the last searched one in at most two
+    /// plays. See [`MctsSearcher::advance_to`].
+    pub fn set_tree_reuse(&mut self, reuse_tree: bool) {
+        self.reuse_tree = reuse_tree;
+        if !reuse_tree {
+            self.searchers.clear();
+        }
+    }
+
+    /// Sets the number of threads to search on, which is 1 by default.
+    ///
+    /// The iteration limit of a search is split between the threads, so searches limited by
+    /// iterations are still reproducible from their seed for the same number of threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_threads(&mut self, n_threads: usize) {
+        assert!(
+            n_threads > 0,
+            "there should be at least one thread to search on."
+        );
+        self.n_threads = n_threads;
+    }
+}
+
+impl<S: TimeSource> Agent for MctsAgent<S> {
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Play {
+        let seed = self.seeds.next_seed();
+        // Each searcher gets an RNG of its own, and the first one gets the one seeded with the
+        // seed of the search, like a single threaded search.
+        let mut thread_seeds = SeedSequence::new(seed);
+        let mut reused_searchers = std::mem::take(&mut self.searchers)
+            .into_iter()
+            .filter_map(|mut searcher| searcher.advance_to(game).then_some(searcher));
+        let mut searchers = (0..self.n_threads)
+            .map(|_| {
+                let (_, rng) = thread_seeds.next_rng();
+                match reused_searchers.next() {
+                    Some(mut searcher) => {
+                        searcher.set_rng(rng);
+                        searcher
+                    }
+                    None => MctsSearcher::new(game.clone(), rng),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let n_visits = searchers
+            .iter()
+            .map(|searcher| searcher.root().n_visits())
+            .collect::<Vec<_>>();
+        let start = self.time_source.now();
+        let elapsed = || self.time_source.now().saturating_sub(start);
+        match searchers.as_mut_slice() {
+            [searcher] => {
+                searcher.search(|root| limits.is_reached(root.n_visits() - n_visits[0], elapsed()))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            searchers => {
+                // The threads only check their share of the iteration limit, since the time
+                // source may not be shared between threads.
+                let max_iterations = limits.iterations.or(limits.time.is_none().then_some(0));
+                let n_threads = searchers.len();
+                search_parallel(
+                    searchers,
+                    || limits.time.map(|time| time.saturating_sub(elapsed())),
+                    |thread, root| {
+                        max_iterations.is_some_and(|max_iterations| {
+                            let share = iteration_share(max_iterations, n_threads, thread);
+                            root.n_visits() - n_visits[thread] >= share
+                        })
+                    },
+                );
+            }
+            #[cfg(target_arch = "wasm32")]
+            _ => unreachable!("the agent should only search on one thread in the browser."),
+        }
+
+        let plays = merge_root_children(&searchers);
+        let play = best_merged_play(&plays);
+        let iterations = searchers
+            .iter()
+            .zip(n_visits)
+            .map(|(searcher, n_visits)| searcher.root().n_visits() - n_visits)
+            .sum();
+        self.info = Some(SearchInfo {
+            iterations,
+            plays: plays
+                .into_iter()
+                .map(|(play, score, n_visits)| PlayInfo {
+                    play,
+                    n_visits,
+                    average_score: score / n_visits as f32,
+                })
+                .collect(),
+            seed: Some(seed),
+        });
+        if self.reuse_tree {
+            self.searchers = searchers;
+        }
+        play
+    }
+
+    fn search_info(&self) -> Option<&SearchInfo> {
+        self.info.as_ref()
+    }
+}
+
+/// The index of a node in a [`Tree`].
+type NodeIndex = u32;
+
+/// The index of the root node of every [`Tree`].
+const ROOT: NodeIndex = 0;
+
+/// A Monte Carlo Tree stored in an arena.
 ///
-/// Usage:
-/// Store the root node and navigate the tree with a [`Cursor`].
+/// Nodes refer to each other by their index in the arena. The children of a node are all added at
+/// once when it's expanded, so they're contiguous and a node only stores the range of their
+/// indices. Nodes don't store their parent, since the [`Cursor`] keeps the path it took from the
+/// root instead.
 #[derive(Clone, Debug)]
-pub struct Node {
+struct Tree<P> {
+    /// All the nodes of the tree, with the root first.
+    nodes: Vec<NodeData<P>>,
+}
+
+/// The data of a node in a [`Tree`], which is inspected through a [`Node`].
+#[derive(Clone, Debug)]
+struct NodeData<P> {
     /// A *valid* play from the parent game state.
     ///
-    /// Invariant: Should only be `None` for the root node,
-    /// in other words: `self.play.is_none() == self.parent.is_none()`
-    play: Option<Play>,
+    /// Invariant: Should only be `None` for the root node.
+    play: Option<P>,
     /// The total score of all rollouts from this node and all its children.
     score: f32,
     /// The total number of rollouts from this node and all its children.
     n_visits: usize,
-    /// The children of this nodes, representing all valid plays from the current game state.
-    children: Vec<NodeRef>,
-    /// The parent node.
-    ///
-    /// Invariants:
-    /// 1. Should only be `None` for the root node
-    /// 2. If it's `Some`, `parent.children` should contain this node.
-    parent: Option<Weak<RefCell<Node>>>,
+    /// The indices of the children of this node, representing all valid plays from the current
+    /// game state.
+    children: Range<NodeIndex>,
 }
 
-impl Node {
-    /// Returns the total score of all rollouts from this node and all its children.
-    pub fn score(&self) -> f32 {
-        self.score
+impl<P> Tree<P> {
+    /// Returns a tree with only a root node.
+    fn new() -> Self {
+        Self {
+            nodes: vec![NodeData::new(None)],
+        }
     }
-    /// Returns the total number of rollouts from this node and all its children.
-    pub fn n_visits(&self) -> usize {
-        self.n_visits
+    /// Returns the root node.
+    fn root(&self) -> Node<'_, P> {
+        Node {
+            tree: self,
+            index: ROOT,
+        }
     }
-    /// Returns the total score devided by the total number of rollouts.
-    pub fn average_score(&self) -> f32 {
-        self.score / self.n_visits as f32
+    fn node(&self, index: NodeIndex) -> &NodeData<P> {
+        &self.nodes[index as usize]
     }
-    /// Returns an iterator over this node's children.
-    pub fn children<'a>(
-        &'a self,
-    ) -> Map<slice::Iter<'a, NodeRef>, impl FnMut(&'a NodeRef) -> Ref<'a, Node>> {
-        self.children.iter().map(|x: &NodeRef| x.borrow())
-    }
-    /// Returns a new root node.
-    fn new_root() -> NodeRef {
-        Rc::new(RefCell::new(Self {
-            play: None,
-            score: 0.0,
-            n_visits: 0,
-            children: Vec::new(),
-            parent: None,
-        }))
-    }
-    /// Adds a child node with the given `play` to `this` node.
-    fn add_child(this: &NodeRef, play: Play) {
-        let node = Self {
-            play: Some(play),
+    fn node_mut(&mut self, index: NodeIndex) -> &mut NodeData<P> {
+        &mut self.nodes[index as usize]
+    }
+    /// Returns the index the next node added to the tree gets.
+    fn next_index(&self) -> NodeIndex {
+        NodeIndex::try_from(self.nodes.len())
+            .expect("a tree should never have more nodes than a node index can address.")
+    }
+    /// Adds a child node for each of the given plays to the given node, and returns the range of
+    /// their indices.
+    ///
+    /// The caller must ensure the node has no children yet.
+    fn add_children(
+        &mut self,
+        index: NodeIndex,
+        plays: impl Iterator<Item = P>,
+    ) -> Range<NodeIndex> {
+        let start = self.next_index();
+        self.nodes
+            .extend(plays.map(|play| NodeData::new(Some(play))));
+        let children = start..self.next_index();
+        self.node_mut(index).children = children.clone();
+        children
+    }
+    /// Returns the child of the given node with the highest UCB1 score or `None` if it has no
+    /// children.
+    fn find_best_child(&self, index: NodeIndex) -> Option<NodeIndex> {
+        let node = self.node(index);
+        let lnn = (node.n_visits as f32).ln();
+        node.children.clone().max_by(|&child0, &child1| {
+            self.node(child0)
+                .ucb1(lnn)
+                .total_cmp(&self.node(child1).ucb1(lnn))
+        })
+    }
+    /// Returns the child of the given node with the highest total score, which is the best play
+    /// from it once the search is over.
+    ///
+    /// The caller must ensure the node has at least one child.
+    fn best_child(&self, index: NodeIndex) -> NodeIndex {
+        self.node(index)
+            .children
+            .clone()
+            .max_by(|&a, &b| self.node(a).score.total_cmp(&self.node(b).score))
+            .expect("an in-progress game should always have at least one possible play.")
+    }
+    /// Returns the child of the given node for the given play, if it has one.
+    fn find_child(&self, index: NodeIndex, play: P) -> Option<NodeIndex>
+    where
+        P: PartialEq,
+    {
+        self.node(index)
+            .children
+            .clone()
+            .find(|&child| self.node(child).play.as_ref() == Some(&play))
+    }
+
+    /// Returns a copy of the subtree below the given node, with that node as its root.
+    fn subtree(&self, index: NodeIndex) -> Self
+    where
+        P: Clone,
+    {
+        let mut subtree = Self {
+            nodes: vec![NodeData {
+                play: None,
+                ..self.node(index).clone()
+            }],
+        };
+        // Copying the nodes in breadth-first order keeps the children of each node contiguous.
+        // Until a copied node is reached, its range of children still has the original indices.
+        let mut next = ROOT;
+        while (next as usize) < subtree.nodes.len() {
+            let children = subtree.node(next).children.clone();
+            let start = subtree.next_index();
+            subtree
+                .nodes
+                .extend(children.map(|child| self.node(child).clone()));
+            subtree.node_mut(next).children = start..subtree.next_index();
+            next += 1;
+        }
+        subtree
+    }
+}
+
+impl<P> NodeData<P> {
+    fn new(play: Option<P>) -> Self {
+        Self {
+            play,
             score: 0.0,
             n_visits: 0,
-            children: Vec::new(),
-            parent: Some(Rc::downgrade(this)),
-        };
-        this.borrow_mut().children.push(Rc::new(RefCell::new(node)));
+            children: 0..0,
+        }
+    }
+    /// Returns the total score devided by the total number of rollouts.
+    fn average_score(&self) -> f32 {
+        self.score / self.n_visits as f32
     }
     /// Calculates the upper confidence bounds (UCB) of this node.
     ///
-    /// `lnn` is the natural log of the total rollouts of the parent node,
-    /// in other words: `lnn = (self.parent.upgrade().expect().n_visits as f32).ln()`
+    /// `lnn` is the natural log of the total rollouts of the parent node.
     /// This function takes `lnn` as a parameter to allow the caller to calculate it once and cache
     /// it, since this function is expected to be called repeatedly over every child of a node when
     /// searching for the best child.
@@ -141,19 +619,6 @@ impl Node {
         self.score += score_update;
         self.n_visits += 1;
     }
-    /// Returns the child with the highest UCB1 score or `None` if this node has no children.
-    fn find_best_child(&self) -> Option<NodeRef> {
-        let lnn = (self.n_visits as f32).ln();
-        self.children
-            .iter()
-            .max_by(|node0: &&NodeRef, node1: &&NodeRef| {
-                node0
-                    .borrow()
-                    .ucb1(lnn)
-                    .total_cmp(&node1.borrow().ucb1(lnn))
-            })
-            .map(Rc::clone)
-    }
     /// Returns `true` if the MCTS algorithm should skip the expansion step and perform a rollout
     /// immediately.
     ///
@@ -164,9 +629,81 @@ impl Node {
     }
 }
 
+/// A node in a Monte Carlo Tree
+///
+/// Each node (aside from the *root* node) stores a play that can be made in the game from its
+/// parent state.
+/// Each node represents a game state that can be reached by applying the play to its parent state
+/// starting from the root.
+///
+/// A *terminal* node is a node that represents a game-over state.
+///
+/// A *leaf* node is a node with can be expanded (is not *terminal*) but hasn't been expanded yet,
+/// so it has no children.
+///
+/// Usage:
+/// Get the root node from an [`MctsSearcher`] and inspect the tree from there.
+pub struct Node<'a, P = Play> {
+    tree: &'a Tree<P>,
+    index: NodeIndex,
+}
+
+impl<'a, P> Node<'a, P> {
+    fn data(&self) -> &'a NodeData<P> {
+        self.tree.node(self.index)
+    }
+    /// Returns the play leading to this node, or `None` for the root node.
+    pub fn play(&self) -> Option<P>
+    where
+        P: Copy,
+    {
+        self.data().play
+    }
+    /// Returns the total score of all rollouts from this node and all its children.
+    pub fn score(&self) -> f32 {
+        self.data().score
+    }
+    /// Returns the total number of rollouts from this node and all its children.
+    pub fn n_visits(&self) -> usize {
+        self.data().n_visits
+    }
+    /// Returns the total score devided by the total number of rollouts.
+    pub fn average_score(&self) -> f32 {
+        self.data().average_score()
+    }
+    /// Returns an iterator over this node's children.
+    pub fn children(&self) -> impl ExactSizeIterator<Item = Node<'a, P>> {
+        let tree = self.tree;
+        self.data()
+            .children
+            .clone()
+            .map(move |index| Node { tree, index })
+    }
+}
+
+impl<P> Clone for Node<'_, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P> Copy for Node<'_, P> {}
+
+impl<P: Debug> Debug for Node<'_, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = self.data();
+        f.debug_struct("Node")
+            .field("play", &data.play)
+            .field("score", &data.score)
+            .field("n_visits", &data.n_visits)
+            .field("n_children", &data.children.len())
+            .finish()
+    }
+}
+
 /// A cursor for populating and navigating the Monte Carlo Tree.
 ///
-/// The cursor holds an instance of [`Game`] and updates its state with the play stored at each
+/// The cursor holds an instance of the game and updates its state with the play stored at each
 /// node as it traverses through the tree.
 ///
 /// [`Cursor::run`] runs one iteration of the MCTS algorithm ending with backpropagating the score
@@ -174,48 +711,54 @@ impl Node {
 ///
 /// This algorithm is implemented referencing this video:
 /// https://www.youtube.com/watch?v=UXW2yZndl7U
-struct Cursor {
-    /// The root node of the tree.
+struct Cursor<'a, G: Playable, R> {
+    /// The tree the cursor is populating.
+    tree: &'a mut Tree<G::Play>,
+    /// The indices of the nodes from the root to the node the cursor is currently pointing at.
     ///
-    /// This is only stored to take shared ownership of the root, which then guarantees that no
-    /// parts of the tree get dropped while the cursor is traversing it.
-    _root_node: NodeRef,
-    /// The node the cursor is currently pointing at.
-    current_node: NodeRef,
+    /// Invariant: Never empty, and starts with [`ROOT`].
+    path: Vec<NodeIndex>,
     /// The player performing the move of the current node.
     current_player: Player,
-    /// An instance of the original game state represented by the root node.
+    /// The original game state represented by the root node.
     ///
     /// This is needed since the cursor only keeps the game state updated as it traverses down the
     /// tree, but not while backpropagating up to the root. After backpropagation is finished, the
     /// cursor will clone this value to restore the game state.
-    original_game: Game,
-    /// The current game state represented by having just performed the play stored in
-    /// `current_node`.
-    game: Game,
+    original_game: &'a G,
+    /// The current game state represented by having just performed the play stored in the
+    /// current node.
+    game: G,
     /// RNG for the random elements in the MCTS algorithm.
-    rng: ThreadRng,
+    rng: R,
 }
 
-impl Cursor {
-    /// Creates a new [`Cursor`] with the given root node and starting game state.
-    fn new(root_node: NodeRef, game: Game) -> Self {
+impl<'a, G: Playable, R: Rng> Cursor<'a, G, R> {
+    /// Creates a new [`Cursor`] with the given tree, starting game state and RNG.
+    fn new(tree: &'a mut Tree<G::Play>, game: &'a G, rng: R) -> Self {
         Self {
-            current_node: root_node.clone(),
-            _root_node: root_node,
-            current_player: game.current_player.other(),
-            original_game: game.clone(),
-            game,
-            rng: thread_rng(),
+            tree,
+            path: vec![ROOT],
+            current_player: game.current_player().other(),
+            original_game: game,
+            game: game.clone(),
+            rng,
         }
     }
+    /// Returns the index of the node the cursor is currently pointing at.
+    fn current_node(&self) -> NodeIndex {
+        *self
+            .path
+            .last()
+            .expect("the path should always start with the root.")
+    }
     /// Updates the current game state by making the given play.
     ///
     /// The caller must ensure the given play is valid for the current game state.
     ///
     /// Returns `ControlFlow::Continue(())` if the game is still going after making the play;
     /// returns `ControlFlow::Break(outcome)` if the game ends.
-    fn mark_tile(&mut self, play: Play) -> ControlFlow<BoardOutcome> {
+    fn mark_tile(&mut self, play: G::Play) -> ControlFlow<BoardOutcome> {
         match self.game.mark_tile(play) {
             MarkTileResult::NoChange => unreachable!(
                 "generated move should always be valid and should never result in NoChange"
@@ -227,11 +770,10 @@ impl Cursor {
     /// Updates the current node to the given `child` and updates the current game state
     /// accordingly.
     ///
-    /// The caller must ensure that `child` is a child of `self.current_node`.
-    fn visit(&mut self, child: NodeRef) -> ControlFlow<BoardOutcome> {
-        let play = child.borrow().play;
-        self.current_node = child;
-        if let Some(play) = play {
+    /// The caller must ensure that `child` is a child of the current node.
+    fn visit(&mut self, child: NodeIndex) -> ControlFlow<BoardOutcome> {
+        self.path.push(child);
+        if let Some(play) = self.tree.node(child).play {
             self.current_player = self.current_player.other();
             self.mark_tile(play)
         } else {
@@ -253,19 +795,15 @@ impl Cursor {
     /// Traverses the tree by selecting the best child at each node until it reaches a leaf node or
     /// a terminal node.
     ///
-    /// See also [`Node`] and [`Node::find_best_child`].
+    /// See also [`Node`] and [`Tree::find_best_child`].
     fn explore(&mut self) -> ControlFlow<BoardOutcome> {
-        loop {
-            let child = self.current_node.borrow().find_best_child();
-            if let Some(child) = child {
-                let () = self.visit(child)?;
-            } else {
-                return ControlFlow::Continue(());
-            }
+        while let Some(child) = self.tree.find_best_child(self.current_node()) {
+            let () = self.visit(child)?;
         }
+        ControlFlow::Continue(())
     }
-    /// Populates the [`Node::children`] field of the current node by enumerating all valid plays
-    /// from the current game state, then moves the cursor into one of the new nodes.
+    /// Adds the children of the current node by enumerating all valid plays from the current game
+    /// state, then moves the cursor into one of the new nodes.
     ///
     /// The caller must ensure that the current node is not a *terminal* node, meaning the current
     /// game state must be [`BoardState::InProgress`].
@@ -273,71 +811,176 @@ impl Cursor {
     /// All possible child nodes are generated at once then shuffled. On future visits, the cursor
     /// can just visit the unvisited children in order and it would still be effectively random.
     fn expand(&mut self) -> ControlFlow<BoardOutcome> {
-        assert!(matches!(self.game.state, BoardState::InProgress));
+        assert!(matches!(self.game.state(), BoardState::InProgress));
 
-        if self.current_node.borrow().should_rollout() {
+        let current_node = self.current_node();
+        if self.tree.node(current_node).should_rollout() {
             return ControlFlow::Continue(());
         }
 
-        let add_moves_for_region = |(region_index, region): (BoardIndex, &Region)| {
-            for (tile_index, _) in region.board.unmarked() {
-                let play = (region_index, tile_index);
-                Node::add_child(&self.current_node, play);
-            }
-        };
+        let children = self
+            .tree
+            .add_children(current_node, self.game.legal_moves());
+        assert!(!children.is_empty(), "an in-progress game should always have at least one possible play, so this node should always have at least one child.");
+        self.tree.nodes[children.start as usize..children.end as usize].shuffle(&mut self.rng);
 
-        if let Some(region_index) = self.game.allowed_region_index() {
-            add_moves_for_region((region_index, &self.game.board[region_index]));
-        } else {
-            self.game.board.unmarked().for_each(add_moves_for_region);
-        };
-        self.current_node
-            .borrow_mut()
-            .children
-            .shuffle(&mut self.rng);
-
-        let child = Rc::clone(self.current_node.borrow().children.first().expect("an in-progress game should always have at least one possible play, so this node should always have at least one child."));
-        self.visit(child)
+        self.visit(children.start)
     }
     /// Runs a simulation of the game from its current state to the end by making random moves,
     /// then returns the outcome.
+    ///
+    /// See [`Playable::rollout`].
     fn rollout(&mut self) -> BoardOutcome {
-        loop {
-            let play = self.rng.generate_move(&self.game);
-            if let ControlFlow::Break(outcome) = self.mark_tile(play) {
-                return outcome;
-            }
-        }
+        self.game.rollout(&mut self.rng)
     }
     /// Traverses from the current node back up to the root node and updates the score of each node
     /// according to the given outcome.
     fn backpropagate(&mut self, outcome: BoardOutcome) {
-        loop {
+        for &index in self.path.iter().rev() {
             // Since the game is played with the players alternating turns, each layer of the tree
             // represents a play by different player. For each node, we assign a score based on
             // whether this is a victory or loss for the player making the move in the current node.
-            let score_update = match outcome {
-                BoardOutcome::Draw => SCORE_DRAW,
-                BoardOutcome::WonBy(winner) => {
-                    if winner == self.current_player {
-                        SCORE_WIN
-                    } else {
-                        SCORE_LOSS
-                    }
-                }
+            let score_update = match Evaluation::of(outcome, self.current_player) {
+                Evaluation::Win => SCORE_WIN,
+                Evaluation::Draw => SCORE_DRAW,
+                Evaluation::Loss => SCORE_LOSS,
             };
-            self.current_node.borrow_mut().update_score(score_update);
+            self.tree.node_mut(index).update_score(score_update);
             self.current_player = self.current_player.other();
-            let parent = if let Some(parent) = &self.current_node.borrow().parent {
-                parent
-                    .upgrade()
-                    .expect("no nodes should be dropped until the search is complete.")
-            } else {
-                break;
-            };
-            self.current_node = parent;
         }
+        self.path.truncate(1);
         self.game = self.original_game.clone();
-        self.current_player = self.game.current_player.other();
+        self.current_player = self.game.current_player().other();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::testing::random_plays;
+
+    /// Returns the number of visits of the given node, and the play, score and number of visits of
+    /// every node below it in depth-first order.
+    fn subtree_stats(node: &Node<'_, Play>) -> (usize, Vec<(Option<Play>, f32, usize)>) {
+        let mut stats = Vec::new();
+        let mut stack = node.children().collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            stats.push((node.play(), node.score(), node.n_visits()));
+            stack.extend(node.children());
+        }
+        (node.n_visits(), stats)
+    }
+
+    /// Returns a searcher that has searched the game after the first `n_plays` of
+    /// [`random_plays`] for the given number of iterations, and those plays.
+    fn searched(n_plays: usize, iterations: usize) -> (MctsSearcher<Game, StdRng>, Vec<Play>) {
+        let plays = random_plays(Default::default(), 0);
+        let mut game = Game::new();
+        for &play in &plays[..n_plays] {
+            game.mark_tile(play);
+        }
+        let mut searcher = MctsSearcher::new(game, StdRng::seed_from_u64(0));
+        searcher.search(|root| root.n_visits() >= iterations);
+        (searcher, plays[n_plays..].to_vec())
+    }
+
+    /// Returns the node below the root of the given searcher reached by the given plays.
+    fn descendant<'a>(searcher: &'a MctsSearcher<Game, StdRng>, plays: &[Play]) -> Node<'a, Play> {
+        plays.iter().fold(searcher.root(), |node, &play| {
+            node.children()
+                .find(|child| child.play() == Some(play))
+                .expect("the searched tree should have a node for every play.")
+        })
+    }
+
+    #[test]
+    fn advance_keeps_subtree() {
+        let (mut searcher, plays) = searched(10, 5000);
+        let expected = subtree_stats(&descendant(&searcher, &plays[..1]));
+        assert!(expected.0 > 0);
+
+        let mut game = searcher.game().clone();
+        game.mark_tile(plays[0]);
+        assert_eq!(searcher.advance(plays[0]), MarkTileResult::TileMarked);
+        assert_eq!(searcher.game(), &game);
+        assert_eq!(searcher.root().play(), None);
+        assert_eq!(subtree_stats(&searcher.root()), expected);
+    }
+
+    #[test]
+    fn advance_to_keeps_subtree() {
+        let (mut searcher, plays) = searched(10, 5000);
+        let expected = subtree_stats(&descendant(&searcher, &plays[..2]));
+        assert!(expected.0 > 0);
+
+        let mut game = searcher.game().clone();
+        assert!(searcher.advance_to(&game));
+        game.mark_tile(plays[0]);
+        game.mark_tile(plays[1]);
+        assert!(searcher.advance_to(&game));
+        assert_eq!(searcher.game(), &game);
+        assert_eq!(subtree_stats(&searcher.root()), expected);
+    }
+
+    #[test]
+    fn advance_to_unreachable_game() {
+        let (mut searcher, plays) = searched(10, 1000);
+        let expected = subtree_stats(&searcher.root());
+        let mut game = searcher.game().clone();
+        for &play in &plays[..3] {
+            game.mark_tile(play);
+        }
+        let original_game = searcher.game().clone();
+        assert!(!searcher.advance_to(&game));
+        assert_eq!(searcher.game(), &original_game);
+        assert_eq!(subtree_stats(&searcher.root()), expected);
+
+        // The agent starts a fresh tree instead, so its search is the same as one without reuse
+        // with the same seed.
+        let limits = SearchLimits::iterations(500);
+        let mut agent = MctsAgent::with_seed(1);
+        agent.set_tree_reuse(true);
+        agent.choose_move(&original_game, &limits);
+        let play = agent.choose_move(&game, &limits);
+        let info = agent.search_info().unwrap().clone();
+        assert_eq!(
+            info.plays.iter().map(|play| play.n_visits).sum::<usize>(),
+            500
+        );
+
+        let mut fresh_agent = MctsAgent::new();
+        fresh_agent.set_seed(info.seed.unwrap());
+        assert_eq!(fresh_agent.choose_move(&game, &limits), play);
+        assert_eq!(fresh_agent.search_info(), Some(&info));
+    }
+
+    #[test]
+    fn reused_search() {
+        let (searcher, plays) = searched(10, 1);
+        let game = searcher.game().clone();
+        let limits = SearchLimits::iterations(1000);
+
+        // Without an earlier tree, a search with reuse is the same as one without.
+        let mut reusing = MctsAgent::with_seed(2);
+        reusing.set_tree_reuse(true);
+        let mut fresh = MctsAgent::with_seed(2);
+        assert_eq!(
+            reusing.choose_move(&game, &limits),
+            fresh.choose_move(&game, &limits)
+        );
+        assert_eq!(reusing.search_info(), fresh.search_info());
+
+        // Afterwards, the search continues in the kept tree: it still runs as many iterations, but
+        // the plays were visited by the earlier search too.
+        let mut next = game.clone();
+        next.mark_tile(plays[0]);
+        next.mark_tile(plays[1]);
+        let play = reusing.choose_move(&next, &limits);
+        assert!(next.legal_moves().any(|legal_play| legal_play == play));
+        let info = reusing.search_info().unwrap();
+        assert_eq!(info.iterations, 1000);
+        assert!(info.plays.iter().map(|play| play.n_visits).sum::<usize>() > 1000);
     }
 }
@@ -1,3 +1,411 @@
 pub mod exhaustive;
 pub mod mct;
 pub mod random;
+
+pub use self::{
+    exhaustive::ExhaustiveAgent,
+    mct::{MctsAgent, MctsSearcher},
+    random::RandomAgent,
+};
+
+use std::{fmt::Debug, time::Duration};
+
+use rand::{rngs::StdRng, seq::IteratorRandom, thread_rng, Rng, SeedableRng};
+
+use crate::{BoardOutcome, BoardState, FastGame, Game, MarkTileResult, NestedGame, Play, Player};
+
+/// A player that chooses plays in a [`Game`] on its own.
+///
+/// All the AIs in this module implement this, so they can be used interchangeably, for example as
+/// `Box<dyn Agent>`.
+pub trait Agent {
+    /// Returns the play this agent would make in the given game, searching within the given
+    /// limits.
+    ///
+    /// The caller must ensure the game is still in progress.
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> Play;
+
+    /// Returns information about the last search of this agent, if it keeps any.
+    fn search_info(&self) -> Option<&SearchInfo> {
+        None
+    }
+}
+
+/// Limits on how long an [`Agent`] may search for a play.
+///
+/// The search stops as soon as any of the limits is reached, and a search with no limits runs a
+/// single iteration. Agents that don't search iteratively ignore the limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SearchLimits {
+    /// The maximum number of iterations of the search.
+    pub iterations: Option<usize>,
+    /// The maximum time the search may take.
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    /// Returns limits that stop the search after the given number of iterations.
+    pub fn iterations(iterations: usize) -> Self {
+        Self {
+            iterations: Some(iterations),
+            time: None,
+        }
+    }
+
+    /// Returns limits that stop the search after the given time.
+    pub fn time(time: Duration) -> Self {
+        Self {
+            iterations: None,
+            time: Some(time),
+        }
+    }
+
+    /// Check if a search that has run the given number of iterations for the given time should
+    /// stop.
+    pub fn is_reached(&self, iterations: usize, elapsed: Duration) -> bool {
+        match (self.iterations, self.time) {
+            (None, None) => iterations >= 1,
+            (max_iterations, max_time) => {
+                max_iterations.is_some_and(|max_iterations| iterations >= max_iterations)
+                    || max_time.is_some_and(|max_time| elapsed >= max_time)
+            }
+        }
+    }
+}
+
+/// Information about the search an [`Agent`] ran to choose a play.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchInfo {
+    /// The number of iterations the search ran.
+    ///
+    /// For [`MctsAgent`] this is the number of rollouts, and for [`ExhaustiveAgent`] the number of
+    /// distinct positions it scored, summed over the depths of an iteratively deepened search.
+    pub iterations: usize,
+    /// What the search found out about each valid play, if the agent keeps track of them.
+    pub plays: Vec<PlayInfo>,
+    /// The seed of the random number generator of the search, if it used one.
+    ///
+    /// Setting this seed on an agent makes its next search from the same game with the same
+    /// iteration limit choose the same play, which makes bad plays reproducible.
+    pub seed: Option<u64>,
+}
+
+/// What an [`Agent`] found out about one play during its search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayInfo {
+    pub play: Play,
+    /// The number of iterations of the search that went through this play, including those of
+    /// earlier searches whose tree was reused.
+    pub n_visits: usize,
+    /// The estimated score of this play for the player making it, from 0 for a loss to 1 for a
+    /// win.
+    pub average_score: f32,
+}
+
+/// A two-player game that the AIs in this module can play.
+pub trait Playable: Clone {
+    /// A play that can be made in the game.
+    type Play: Copy + PartialEq + Debug;
+
+    fn state(&self) -> BoardState;
+
+    /// Returns the player who makes the next play.
+    fn current_player(&self) -> Player;
+
+    /// Returns an iterator over all the valid plays for the current player.
+    fn legal_moves(&self) -> impl Iterator<Item = Self::Play>;
+
+    /// Makes the given play for the current player.
+    fn mark_tile(&mut self, play: Self::Play) -> MarkTileResult;
+
+    /// Returns a random valid play for the current player, chosen uniformly among all of them
+    /// unless the game has a better-suited distribution.
+    ///
+    /// The caller must ensure the game is still in progress.
+    fn random_play(&self, rng: &mut impl Rng) -> Self::Play {
+        self.legal_moves()
+            .choose(rng)
+            .expect("an in-progress game should always have at least one possible play.")
+    }
+
+    /// Plays random moves until the game is over, then returns the outcome.
+    ///
+    /// The caller must ensure the game is still in progress. The game may or may not be left in
+    /// its final state.
+    fn rollout(&mut self, rng: &mut impl Rng) -> BoardOutcome {
+        random::rollout(self, rng)
+    }
+}
+
+impl Playable for Game {
+    type Play = Play;
+
+    fn state(&self) -> BoardState {
+        self.state
+    }
+
+    fn current_player(&self) -> Player {
+        self.current_player
+    }
+
+    fn legal_moves(&self) -> impl Iterator<Item = Self::Play> {
+        self.legal_moves()
+    }
+
+    fn mark_tile(&mut self, play: Self::Play) -> MarkTileResult {
+        self.mark_tile(play)
+    }
+
+    /// Chooses a region uniformly first and then a tile within it.
+    fn random_play(&self, rng: &mut impl Rng) -> Self::Play {
+        self.legal_moves()
+            .choose_by_region(rng)
+            .expect("an in-progress game should always have at least one possible play.")
+    }
+
+    /// Runs the simulation on a [`FastGame`] copy of this game if its rules are supported, which
+    /// leaves this game untouched.
+    fn rollout(&mut self, rng: &mut impl Rng) -> BoardOutcome {
+        if FastGame::supports(&self.rules) {
+            FastGame::from(&*self).rollout(rng)
+        } else {
+            random::rollout(self, rng)
+        }
+    }
+}
+
+impl Playable for FastGame {
+    type Play = Play;
+
+    fn state(&self) -> BoardState {
+        self.state()
+    }
+
+    fn current_player(&self) -> Player {
+        self.current_player()
+    }
+
+    fn legal_moves(&self) -> impl Iterator<Item = Self::Play> {
+        self.legal_moves()
+    }
+
+    fn mark_tile(&mut self, play: Self::Play) -> MarkTileResult {
+        self.mark_tile(play)
+    }
+
+    fn random_play(&self, rng: &mut impl Rng) -> Self::Play {
+        self.random_play(rng)
+    }
+
+    fn rollout(&mut self, rng: &mut impl Rng) -> BoardOutcome {
+        self.rollout(rng)
+    }
+}
+
+impl Playable for NestedGame {
+    type Play = usize;
+
+    fn state(&self) -> BoardState {
+        self.state()
+    }
+
+    fn current_player(&self) -> Player {
+        self.current_player()
+    }
+
+    fn legal_moves(&self) -> impl Iterator<Item = Self::Play> {
+        self.legal_moves()
+    }
+
+    fn mark_tile(&mut self, play: Self::Play) -> MarkTileResult {
+        self.mark_tile(play)
+    }
+}
+
+/// The seeds of the random number generators of the searches of an agent.
+///
+/// Every search gets an RNG of its own, seeded from the next seed in the sequence, so any search
+/// can be repeated by starting a sequence from its seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SeedSequence {
+    next: u64,
+}
+
+impl SeedSequence {
+    /// The increment between consecutive seeds, from SplitMix64.
+    const INCREMENT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    /// Returns a sequence starting from the given seed.
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { next: seed }
+    }
+
+    /// Returns a sequence starting from a random seed.
+    pub(crate) fn random() -> Self {
+        Self::new(thread_rng().gen())
+    }
+
+    /// Returns the next seed.
+    pub(crate) fn next_seed(&mut self) -> u64 {
+        let seed = self.next;
+        self.next = seed.wrapping_add(Self::INCREMENT);
+        seed
+    }
+
+    /// Returns the next seed and an RNG seeded with it.
+    pub(crate) fn next_rng(&mut self) -> (u64, StdRng) {
+        let seed = self.next_seed();
+        (seed, StdRng::seed_from_u64(seed))
+    }
+}
+
+/// The result of a finished game from the point of view of one player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Evaluation {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Evaluation {
+    /// Evaluates the given outcome for the given player.
+    ///
+    /// The AIs should always evaluate outcomes through this rather than comparing the winner with
+    /// the player who made the last play, since that player may have lost by completing a line in
+    /// [`GameMode::Misere`](crate::GameMode::Misere). The winner of a [`BoardOutcome`] already
+    /// accounts for the game mode.
+    pub fn of(outcome: BoardOutcome, player: Player) -> Self {
+        match outcome {
+            BoardOutcome::Draw => Evaluation::Draw,
+            BoardOutcome::WonBy(winner) if winner == player => Evaluation::Win,
+            BoardOutcome::WonBy(_) => Evaluation::Loss,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::LegalMoves, BoardIndex};
+
+    /// Returns a game after the given number of plays by a seeded [`RandomAgent`].
+    fn random_game(n_plays: usize) -> Game {
+        let mut agent = RandomAgent::with_seed(0);
+        let mut game = Game::new();
+        for _ in 0..n_plays {
+            let play = agent.choose_move(&game, &SearchLimits::default());
+            game.try_mark_tile(play).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn random_plays_choose_a_region_first() {
+        // One open tile in the upper left region and nine in the upper one, so choosing a region
+        // first picks the lone tile about half of the time instead of a tenth.
+        let plays = LegalMoves::from_mask(1 | 0x1FF << 9);
+        let mut rng = StdRng::seed_from_u64(0);
+        let n_lone = (0..1000)
+            .filter(|_| plays.choose_by_region(&mut rng).unwrap().0 == BoardIndex::UpperLeft)
+            .count();
+        assert!((400..600).contains(&n_lone), "{n_lone}");
+        assert_eq!(LegalMoves::default().choose_by_region(&mut rng), None);
+    }
+
+    #[test]
+    fn fast_game_random_plays_match_game() {
+        for seed in 0..10 {
+            let mut game = Game::new();
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut fast_rng = rng.clone();
+            while game.state == BoardState::InProgress {
+                let play = Playable::random_play(&game, &mut rng);
+                assert_eq!(FastGame::from(&game).random_play(&mut fast_rng), play);
+                game.try_mark_tile(play).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn random_agent_is_deterministic() {
+        let game = random_game(10);
+        let plays = |seed| {
+            let mut agent = RandomAgent::with_seed(seed);
+            (0..10)
+                .map(|_| agent.choose_move(&game, &SearchLimits::default()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(plays(1), plays(1));
+        assert_eq!(random_game(30), random_game(30));
+    }
+
+    #[test]
+    fn mcts_agent_is_deterministic() {
+        let limits = SearchLimits::iterations(500);
+        for game in [Game::new(), random_game(20)] {
+            let mut agent0 = MctsAgent::with_seed(42);
+            let mut agent1 = MctsAgent::with_seed(42);
+            assert_eq!(
+                agent0.choose_move(&game, &limits),
+                agent1.choose_move(&game, &limits)
+            );
+            let info = agent0.search_info().unwrap();
+            assert_eq!(info, agent1.search_info().unwrap());
+            assert_eq!(info.seed, Some(42));
+            assert_eq!(info.iterations, 500);
+        }
+    }
+
+    #[test]
+    fn mcts_search_is_reproducible_from_its_seed() {
+        let limits = SearchLimits::iterations(300);
+        let mut agent = MctsAgent::with_seed(7);
+        agent.choose_move(&Game::new(), &limits);
+        let game = random_game(15);
+        let play = agent.choose_move(&game, &limits);
+        let info = agent.search_info().unwrap().clone();
+        assert_ne!(info.seed, Some(7));
+
+        let mut agent = MctsAgent::new();
+        agent.set_seed(info.seed.unwrap());
+        assert_eq!(agent.choose_move(&game, &limits), play);
+        assert_eq!(agent.search_info(), Some(&info));
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn parallel_mcts_agent_is_deterministic() {
+        let limits = SearchLimits::iterations(1001);
+        let game = random_game(10);
+        let search = || {
+            let mut agent = MctsAgent::with_seed(3);
+            agent.set_threads(4);
+            let play = agent.choose_move(&game, &limits);
+            (play, agent.search_info().unwrap().clone())
+        };
+        let (play, info) = search();
+        assert_eq!(info.iterations, 1001);
+        assert_eq!(
+            info.plays.iter().map(|play| play.n_visits).sum::<usize>(),
+            1001
+        );
+        assert_eq!(search(), (play, info));
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn parallel_mcts_agent_stops_on_time() {
+        // Every reading of the time source takes 50 ms, so the search stops on the second check of
+        // the remaining time instead of running forever.
+        let time = std::cell::Cell::new(Duration::ZERO);
+        let mut agent = MctsAgent::with_time_source(|| {
+            time.set(time.get() + Duration::from_millis(50));
+            time.get()
+        });
+        agent.set_threads(2);
+        let game = random_game(10);
+        let play = agent.choose_move(&game, &SearchLimits::time(Duration::from_millis(100)));
+        assert!(game.legal_moves().any(|legal_play| legal_play == play));
+        assert!(agent.search_info().unwrap().iterations > 0);
+    }
+}
@@ -1,35 +1,92 @@
-use rand::{rngs::ThreadRng, seq::IteratorRandom, thread_rng};
+use rand::{thread_rng, Rng};
 
-use crate::{BoardIndex, BoardState, Game, MarkTileResult};
+use crate::{BoardOutcome, BoardState, Game, MarkTileResult, Play};
 
-pub fn make_move(game: &mut Game) {
+use super::{Agent, Playable, SearchInfo, SearchLimits, SeedSequence};
+
+pub fn make_move<G: Playable>(game: &mut G) {
     let play = thread_rng().generate_move(game);
     let result = game.mark_tile(play);
     assert!(!matches!(result, MarkTileResult::NoChange));
 }
 
-pub(crate) trait GenerateMove {
-    fn generate_move(&mut self, game: &Game) -> (BoardIndex, BoardIndex);
+/// Makes random moves in the given game until it's over, then returns the outcome.
+///
+/// The caller must ensure the game is still in progress.
+pub fn rollout<G: Playable>(game: &mut G, rng: &mut impl Rng) -> BoardOutcome {
+    loop {
+        let play = rng.generate_move(game);
+        if let MarkTileResult::OutcomeDecided(outcome) = game.mark_tile(play) {
+            return outcome;
+        }
+    }
 }
 
-impl GenerateMove for ThreadRng {
-    fn generate_move(&mut self, game: &Game) -> (BoardIndex, BoardIndex) {
-        assert!(matches!(game.state, BoardState::InProgress));
+/// An [`Agent`] that makes a random valid play.
+#[derive(Debug, Clone)]
+pub struct RandomAgent {
+    seeds: SeedSequence,
+    info: Option<SearchInfo>,
+}
+
+impl RandomAgent {
+    /// Returns an agent with a random seed.
+    pub fn new() -> Self {
+        Self {
+            seeds: SeedSequence::random(),
+            info: None,
+        }
+    }
+
+    /// Returns an agent whose first play is chosen with the given seed.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seeds: SeedSequence::new(seed),
+            info: None,
+        }
+    }
+
+    /// Sets the seed of the next play.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seeds = SeedSequence::new(seed);
+    }
+}
 
-        let region_index = game.allowed_region_index().unwrap_or_else(|| {
-            game.board
-                .unmarked()
-                .choose(self)
-                .expect("an in-progress game should always have at least incomplete region.")
-                .0
+impl Default for RandomAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Agent for RandomAgent {
+    fn choose_move(&mut self, game: &Game, _limits: &SearchLimits) -> Play {
+        let (seed, mut rng) = self.seeds.next_rng();
+        let play = rng.generate_move(game);
+        self.info = Some(SearchInfo {
+            iterations: 0,
+            plays: Vec::new(),
+            seed: Some(seed),
         });
-        let tile_index = game.board[region_index]
-            .board
-            .unmarked()
-            .choose(self)
-            .expect("an incomplete region should always have at least one unmarked tile.")
-            .0;
-
-        (region_index, tile_index)
+        play
+    }
+
+    fn search_info(&self) -> Option<&SearchInfo> {
+        self.info.as_ref()
+    }
+}
+
+/// Picks random valid plays.
+pub(crate) trait GenerateMove {
+    /// Returns a random valid play for the current player.
+    ///
+    /// The caller must ensure the game is still in progress.
+    fn generate_move<G: Playable>(&mut self, game: &G) -> G::Play;
+}
+
+impl<R: Rng> GenerateMove for R {
+    fn generate_move<G: Playable>(&mut self, game: &G) -> G::Play {
+        assert!(matches!(game.state(), BoardState::InProgress));
+
+        game.random_play(self)
     }
 }
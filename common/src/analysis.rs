@@ -0,0 +1,121 @@
+use crate::{
+    Board, BoardIndex, BoardItem, BoardOutcome, BoardState, Game, MarkTileResult, Play, Player,
+};
+
+/// Tactical facts about a [`Game`] from the point of view of the player to move, as returned by
+/// [`Game::analyze`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// For every region, the unmarked tiles that would give each player three in a line in it.
+    ///
+    /// Only regions that are in progress have any, since marking a tile never changes who won a
+    /// region.
+    pub region_threats: Board<Threats>,
+    /// The regions in progress that would give each player three regions in a line if they won
+    /// them, under the rules of the game.
+    pub game_threats: Threats,
+    /// The valid plays that send the opponent to a region they can't play in, which lets them play
+    /// in any region instead.
+    pub free_move_plays: Vec<Play>,
+    /// The valid plays that immediately win the game for the player to move.
+    pub winning_plays: Vec<Play>,
+}
+
+/// The indices at which each player would complete three in a line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Threats {
+    pub circle: Vec<BoardIndex>,
+    pub cross: Vec<BoardIndex>,
+}
+
+impl Threats {
+    /// Returns the indices at which the given player would complete three in a line.
+    pub fn of(&self, player: Player) -> &[BoardIndex] {
+        match player {
+            Player::Circle => &self.circle,
+            Player::Cross => &self.cross,
+        }
+    }
+
+    /// Returns the threats on the given board, where `is_open` tells if an item can still be
+    /// taken, and `is_marked_by` if it already counts for a player.
+    fn find<T>(
+        board: &Board<T>,
+        is_open: impl Fn(&T) -> bool,
+        is_marked_by: impl Fn(&T, Player) -> bool,
+    ) -> Self {
+        let completing = |player| {
+            board
+                .enumerate()
+                .filter(|(_, item)| is_open(item))
+                .filter(|&(index, _)| {
+                    BoardIndex::ALL_LINES.iter().any(|line| {
+                        line.contains(&index)
+                            && line
+                                .iter()
+                                .all(|&i| i == index || is_marked_by(&board[i], player))
+                    })
+                })
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        Self {
+            circle: completing(Player::Circle),
+            cross: completing(Player::Cross),
+        }
+    }
+}
+
+impl Game {
+    /// Returns the tactical facts about this game for the player to move.
+    ///
+    /// Threats are found by checking [`BoardIndex::ALL_LINES`], and the plays that grant a free
+    /// move or win are found by trying every valid play, so the analysis follows the rules of the
+    /// game.
+    pub fn analyze(&self) -> Analysis {
+        let mut region_threats = Board::<Threats>::default();
+        for (index, region) in self.board.enumerate() {
+            if region.is_markable() {
+                region_threats[index] = Threats::find(
+                    &region.board,
+                    BoardItem::is_markable,
+                    BoardItem::is_marked_by,
+                );
+            }
+        }
+
+        let game_threats = Threats::find(&self.board, BoardItem::is_markable, |region, player| {
+            self.rules.is_region_marked_by(region, player)
+        });
+
+        let mut free_move_plays = Vec::new();
+        let mut winning_plays = Vec::new();
+        let win = BoardState::Complete(BoardOutcome::WonBy(self.current_player));
+        for play in self.legal_moves() {
+            let mut game = self.clone();
+            match game.mark_tile(play) {
+                MarkTileResult::NoChange => {
+                    unreachable!("a legal play should never result in NoChange.")
+                }
+                MarkTileResult::TileMarked => {
+                    if game.allowed_region_index().is_none() {
+                        free_move_plays.push(play);
+                    }
+                }
+                MarkTileResult::OutcomeDecided(_) => {
+                    if game.state == win {
+                        winning_plays.push(play);
+                    }
+                }
+            }
+        }
+
+        Analysis {
+            region_threats,
+            game_threats,
+            free_move_plays,
+            winning_plays,
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BoardIndex, BoardItem, BoardOutcome, BoardState, Player};
+
+/// The shape of a board: how many items it has and which of them make up a line.
+///
+/// Items are indexed row by row from the upper left, the same as `usize::from(BoardIndex)` on
+/// the standard board.
+pub trait Geometry {
+    /// Returns the number of items on a board.
+    fn n_items(&self) -> usize;
+
+    /// Check if there's at least one line of items for whose indices `is_marked` is `true`.
+    fn has_line(&self, is_marked: impl Fn(usize) -> bool) -> bool;
+
+    /// Get the state of a board with the given items.
+    ///
+    /// Panics if the number of items isn't [`Geometry::n_items`].
+    fn get_state<T: BoardItem>(&self, items: &[T]) -> BoardState {
+        assert_eq!(
+            items.len(),
+            self.n_items(),
+            "a board should have as many items as its geometry."
+        );
+        for player in [Player::Circle, Player::Cross] {
+            if self.has_line(|index| items[index].is_marked_by(player)) {
+                return BoardState::Complete(BoardOutcome::WonBy(player));
+            }
+        }
+        if items.iter().any(|item| item.is_markable()) {
+            BoardState::InProgress
+        } else {
+            BoardState::Complete(BoardOutcome::Draw)
+        }
+    }
+}
+
+/// The standard 3x3 board with three-in-a-line, indexed by [`BoardIndex`].
+///
+/// This is what [`Board`](crate::Board) uses unless given a shorter line length. Its lines are the
+/// constant [`BoardIndex::ALL_LINES`], so checking them costs nothing more than before there were
+/// other geometries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Standard;
+
+impl Geometry for Standard {
+    fn n_items(&self) -> usize {
+        BoardIndex::N
+    }
+
+    fn has_line(&self, is_marked: impl Fn(usize) -> bool) -> bool {
+        BoardIndex::ALL_LINES
+            .iter()
+            .any(|indices| indices.iter().all(|&index| is_marked(usize::from(index))))
+    }
+}
+
+/// A square board of any size on which a line is any `line_length` consecutive items in a row,
+/// a column or a diagonal.
+///
+/// `Grid::new(3, 3)` has the same lines as [`Standard`], but they're computed when the grid is
+/// created and checked from memory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "GridSize", into = "GridSize")]
+pub struct Grid {
+    size: usize,
+    line_length: usize,
+    /// The indices of the items of every line.
+    lines: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    /// The largest number of rows and columns of a grid.
+    pub const MAX_SIZE: usize = 16;
+
+    /// Returns a `size` by `size` grid on which lines are `line_length` items long.
+    ///
+    /// Panics if `size` is greater than [`Grid::MAX_SIZE`], or if `line_length` is 0 or greater
+    /// than `size`.
+    pub fn new(size: usize, line_length: usize) -> Self {
+        assert!(
+            size <= Self::MAX_SIZE,
+            "a grid should have at most {} rows and columns.",
+            Self::MAX_SIZE
+        );
+        assert!(
+            0 < line_length && line_length <= size,
+            "a line should be between 1 and {size} items long."
+        );
+
+        // Rows, columns, diagonals and anti-diagonals as `(row, column)` steps.
+        const DIRECTIONS: [(usize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+        let mut lines = Vec::new();
+        for (row_step, column_step) in DIRECTIONS {
+            for row in 0..size {
+                for column in 0..size {
+                    let last_row = row + row_step * (line_length - 1);
+                    let last_column = column as isize + column_step * (line_length as isize - 1);
+                    if last_row >= size || !(0..size as isize).contains(&last_column) {
+                        continue;
+                    }
+                    let line = (0..line_length)
+                        .map(|i| {
+                            let column = column as isize + column_step * i as isize;
+                            (row + row_step * i) * size + column as usize
+                        })
+                        .collect();
+                    lines.push(line);
+                }
+            }
+        }
+
+        Self {
+            size,
+            line_length,
+            lines,
+        }
+    }
+
+    /// Returns the number of rows and columns of the grid.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of items in a line.
+    pub fn line_length(&self) -> usize {
+        self.line_length
+    }
+
+    /// Returns the indices of the items of every line.
+    pub fn lines(&self) -> &[Vec<usize>] {
+        &self.lines
+    }
+
+    /// Returns the 3x3 grid on which lines are `line_length` items long, which has the layout of
+    /// [`Standard`].
+    ///
+    /// Panics if `line_length` is 0 or greater than 3.
+    pub(crate) fn standard(line_length: usize) -> &'static Self {
+        static GRIDS: OnceLock<[Grid; 3]> = OnceLock::new();
+        assert!(
+            (1..=3).contains(&line_length),
+            "a line on the standard board should be between 1 and 3 items long."
+        );
+        &GRIDS.get_or_init(|| [1, 2, 3].map(|line_length| Self::new(3, line_length)))
+            [line_length - 1]
+    }
+}
+
+impl Default for Grid {
+    /// Returns the standard 3x3 grid with three-in-a-line.
+    fn default() -> Self {
+        Self::new(3, 3)
+    }
+}
+
+impl Geometry for Grid {
+    fn n_items(&self) -> usize {
+        self.size
+            .checked_mul(self.size)
+            .expect("the size of a grid should be bounded.")
+    }
+
+    fn has_line(&self, is_marked: impl Fn(usize) -> bool) -> bool {
+        self.lines
+            .iter()
+            .any(|indices| indices.iter().all(|&index| is_marked(index)))
+    }
+}
+
+/// The serialized form of a [`Grid`], whose lines are recomputed when deserializing.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct GridSize {
+    size: usize,
+    line_length: usize,
+}
+
+impl From<Grid> for GridSize {
+    fn from(grid: Grid) -> Self {
+        Self {
+            size: grid.size,
+            line_length: grid.line_length,
+        }
+    }
+}
+
+impl TryFrom<GridSize> for Grid {
+    type Error = InvalidGridSize;
+
+    fn try_from(GridSize { size, line_length }: GridSize) -> Result<Self, Self::Error> {
+        if size <= Self::MAX_SIZE && 0 < line_length && line_length <= size {
+            Ok(Self::new(size, line_length))
+        } else {
+            Err(InvalidGridSize { size, line_length })
+        }
+    }
+}
+
+/// The error returned when deserializing a [`Grid`] that is too large or whose lines don't fit on
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidGridSize {
+    pub size: usize,
+    pub line_length: usize,
+}
+
+impl Display for InvalidGridSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.size > Grid::MAX_SIZE {
+            write!(
+                f,
+                "a {}x{} grid is larger than the largest {}x{} grid",
+                self.size,
+                self.size,
+                Grid::MAX_SIZE,
+                Grid::MAX_SIZE
+            )
+        } else {
+            write!(
+                f,
+                "lines of {} items don't fit on a {}x{} grid",
+                self.line_length, self.size, self.size
+            )
+        }
+    }
+}
+
+impl Error for InvalidGridSize {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{random_positions, rule_sets},
+        Board, Tile,
+    };
+
+    #[test]
+    fn default_grid_matches_standard() {
+        let grid = Grid::default();
+        assert_eq!(grid.lines().len(), BoardIndex::ALL_LINES.len());
+        for rules in rule_sets() {
+            for game in random_positions(rules, 0) {
+                for region in game.board.tiles {
+                    assert_eq!(
+                        grid.get_state(&region.board.tiles),
+                        region.board.get_state()
+                    );
+                    assert_eq!(
+                        region.board.get_state_with_line_length(3),
+                        region.board.get_state()
+                    );
+                }
+                let last_player = game.current_player.other();
+                assert_eq!(
+                    rules.get_state_with_line_length(3, &game.board, last_player),
+                    rules.get_state(&game.board, last_player)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shorter_lines() {
+        let mut board = Board::<Tile>::default();
+        board[BoardIndex::Center] = Tile::Marked(Player::Cross);
+        board[BoardIndex::LowerRight] = Tile::Marked(Player::Cross);
+        assert_eq!(board.get_state(), BoardState::InProgress);
+        assert_eq!(
+            board.get_state_with_line_length(2),
+            BoardState::Complete(BoardOutcome::WonBy(Player::Cross))
+        );
+    }
+
+    #[test]
+    fn grid_lines() {
+        // Every row and column has two lines, and each diagonal direction has four.
+        let grid = Grid::new(4, 3);
+        assert_eq!(grid.n_items(), 16);
+        assert_eq!(grid.lines().len(), 2 * 4 * 2 + 2 * 4);
+        assert!(grid.lines().contains(&vec![1, 6, 11]));
+        assert!(grid.lines().contains(&vec![7, 10, 13]));
+        assert!(!grid.lines().contains(&vec![2, 7, 12]));
+    }
+
+    #[test]
+    fn grid_size_bounds() {
+        let too_large = GridSize {
+            size: Grid::MAX_SIZE + 1,
+            line_length: 3,
+        };
+        assert!(Grid::try_from(too_large).is_err());
+        let too_large = GridSize {
+            size: usize::MAX,
+            line_length: 3,
+        };
+        assert!(Grid::try_from(too_large).is_err());
+        let too_long = GridSize {
+            size: 3,
+            line_length: 4,
+        };
+        assert!(Grid::try_from(too_long).is_err());
+        let largest = GridSize {
+            size: Grid::MAX_SIZE,
+            line_length: 5,
+        };
+        assert_eq!(
+            Grid::try_from(largest).map(|grid| grid.n_items()),
+            Ok(Grid::MAX_SIZE * Grid::MAX_SIZE)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn standard_line_length_bounds() {
+        Board::<Tile>::default().get_state_with_line_length(4);
+    }
+}
@@ -29,6 +29,25 @@ impl BoardIndex {
         [Self::UpperLeft, Self::Center, Self::LowerRight],
         [Self::LowerLeft, Self::Center, Self::UpperRight],
     ];
+
+    /// Returns the row of this index, counting from 0 at the top.
+    pub fn row(self) -> usize {
+        usize::from(self) / 3
+    }
+
+    /// Returns the column of this index, counting from 0 at the left.
+    pub fn column(self) -> usize {
+        usize::from(self) % 3
+    }
+
+    /// Returns the index at the given row and column, or `None` if either is out of bounds.
+    pub fn from_row_column(row: usize, column: usize) -> Option<Self> {
+        if row < 3 && column < 3 {
+            Self::try_from(row * 3 + column).ok()
+        } else {
+            None
+        }
+    }
 }
 
 impl From<BoardIndex> for usize {
@@ -1,3 +1,4 @@
+pub mod geometry;
 pub mod index;
 pub mod item;
 #[allow(clippy::module_inception)]
@@ -8,6 +9,7 @@ use iter::Unmarked;
 use serde::{Deserialize, Serialize};
 
 pub use {
+    geometry::{Geometry, Grid, Standard},
     index::BoardIndex,
     item::BoardItem,
     iter::BoardEnumerate,
@@ -23,41 +25,44 @@ pub struct Board<T> {
 }
 
 impl<T> Board<T> {
-    pub fn enumerate(&self) -> BoardEnumerate<T> {
+    pub fn enumerate(&self) -> BoardEnumerate<'_, T> {
         BoardEnumerate::from(self)
     }
+
+    /// Check if there's at least one three-in-a-line of items for which `is_marked` is `true`.
+    pub fn has_line(&self, is_marked: impl Fn(&T) -> bool) -> bool {
+        Standard.has_line(|index| is_marked(&self.tiles[index]))
+    }
+
+    /// Check if there's at least one line of `line_length` items in a row, a column or a diagonal
+    /// for which `is_marked` is `true`.
+    ///
+    /// Panics if `line_length` is 0 or greater than 3.
+    pub fn has_line_of_length(&self, line_length: usize, is_marked: impl Fn(&T) -> bool) -> bool {
+        Grid::standard(line_length).has_line(|index| is_marked(&self.tiles[index]))
+    }
 }
 
 impl<T: BoardItem> Board<T> {
-    pub fn unmarked(&self) -> Unmarked<T> {
+    pub fn unmarked(&self) -> Unmarked<'_, T> {
         Unmarked::from(self)
     }
 
     /// Get the state of the board.
     pub fn get_state(&self) -> BoardState {
-        if self.check_player(Player::Circle) {
-            BoardState::Complete(BoardOutcome::WonBy(Player::Circle))
-        } else if self.check_player(Player::Cross) {
-            BoardState::Complete(BoardOutcome::WonBy(Player::Cross))
-        } else if self.is_filled() {
-            BoardState::Complete(BoardOutcome::Draw)
-        } else {
-            BoardState::InProgress
-        }
+        Standard.get_state(&self.tiles)
     }
 
-    /// Check if the specified player has at least one three-in-a-line.
-    fn check_player(&self, player: Player) -> bool {
-        BoardIndex::ALL_LINES.iter().any(|indices| {
-            indices
-                .iter()
-                .all(|&index| self[index].is_marked_by(player))
-        })
+    /// Get the state of the board when its lines are `line_length` items long.
+    ///
+    /// Panics if `line_length` is 0 or greater than 3.
+    pub fn get_state_with_line_length(&self, line_length: usize) -> BoardState {
+        Grid::standard(line_length).get_state(&self.tiles)
     }
 
-    /// Check if all the tiles in the region have been marked.
-    fn is_filled(&self) -> bool {
-        !self.tiles.iter().any(|tile| tile.is_markable())
+    /// Check if the specified player has at least one three-in-a-line.
+    pub(crate) fn check_player(&self, player: Player) -> bool {
+        self.has_line(|item| item.is_marked_by(player))
     }
 }
 
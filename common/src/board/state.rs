@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::Player;
+use crate::{BoardItem, Player};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BoardState {
@@ -14,3 +14,13 @@ pub enum BoardOutcome {
     Draw,
     WonBy(Player),
 }
+
+impl BoardItem for BoardState {
+    fn is_markable(&self) -> bool {
+        matches!(*self, BoardState::InProgress)
+    }
+
+    fn is_marked_by(&self, player: Player) -> bool {
+        matches!(*self, BoardState::Complete(BoardOutcome::WonBy(p)) if p == player)
+    }
+}
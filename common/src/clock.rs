@@ -0,0 +1,386 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BoardIndex, BoardItem, BoardOutcome, BoardState, Game, Player, Tile};
+
+/// How much time each player gets to make their plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeControl {
+    /// Each player has the given time for the whole game.
+    SuddenDeath { total: Duration },
+    /// Each player starts with `total`, and gets `increment` added after each of their plays.
+    Increment {
+        total: Duration,
+        increment: Duration,
+    },
+    /// Each player has the given time for every play, and unused time doesn't carry over.
+    PerMove { per_move: Duration },
+}
+
+impl TimeControl {
+    /// Returns the time each player starts with.
+    pub fn initial_time(&self) -> Duration {
+        match *self {
+            Self::SuddenDeath { total } | Self::Increment { total, .. } => total,
+            Self::PerMove { per_move } => per_move,
+        }
+    }
+}
+
+/// A source of the current time, as the time elapsed since some fixed starting point.
+///
+/// Closures returning a [`Duration`] are time sources, which makes it easy to use a fake time in
+/// tests or a platform clock where [`Instant`](std::time::Instant) isn't available, such as in the
+/// browser, where it panics. `Instant` is only a time source on other targets for that reason.
+pub trait TimeSource {
+    fn now(&self) -> Duration;
+}
+
+impl<F: Fn() -> Duration> TimeSource for F {
+    fn now(&self) -> Duration {
+        self()
+    }
+}
+
+/// A time source that never advances, for games played without a clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Untimed;
+
+impl TimeSource for Untimed {
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TimeSource for Instant {
+    /// Returns the time elapsed since this instant.
+    fn now(&self) -> Duration {
+        self.elapsed()
+    }
+}
+
+/// A chess clock for the two players of a game.
+///
+/// At most one player's clock runs at a time. [`Clock::press`] ends the turn of the running
+/// player, charging them for the time it took, and starts the other player's clock.
+///
+/// A clock is serialized as its [`ClockState`], without its time source. To load it, deserialize
+/// the state and restore the clock with [`Clock::from_state`] and the time source to measure it
+/// with. The clock of the running player restarts then, so the time between saving and loading the
+/// clock isn't charged to anyone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(into = "ClockState", bound(serialize = "S: TimeSource + Clone"))]
+pub struct Clock<S> {
+    time_control: TimeControl,
+    source: S,
+    /// The remaining time of each player when their clock was last stopped, indexed by
+    /// [`player_index`].
+    remaining: [Duration; 2],
+    /// The player whose clock is running and the time their turn started at.
+    running: Option<(Player, Duration)>,
+}
+
+impl<S: TimeSource> Clock<S> {
+    /// Returns a stopped clock with the initial time of the given time control for both players.
+    pub fn new(time_control: TimeControl, source: S) -> Self {
+        Self {
+            time_control,
+            source,
+            remaining: [time_control.initial_time(); 2],
+            running: None,
+        }
+    }
+
+    /// Returns a clock in the given state, whose running player's clock, if any, starts now.
+    pub fn from_state(state: ClockState, source: S) -> Self {
+        let mut clock = Self {
+            time_control: state.time_control,
+            source,
+            remaining: [state.circle_remaining, state.cross_remaining],
+            running: None,
+        };
+        if let Some(player) = state.running_player {
+            clock.start(player);
+        }
+        clock
+    }
+
+    /// Returns the current state of this clock, counting the current turn of the running player.
+    pub fn state(&self) -> ClockState {
+        ClockState {
+            time_control: self.time_control,
+            circle_remaining: self.remaining(Player::Circle),
+            cross_remaining: self.remaining(Player::Cross),
+            running_player: self.running_player(),
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    /// Returns the player whose clock is running, if any.
+    pub fn running_player(&self) -> Option<Player> {
+        self.running.map(|(player, _)| player)
+    }
+
+    /// Returns the remaining time of the given player, counting the current turn if their clock
+    /// is running.
+    pub fn remaining(&self, player: Player) -> Duration {
+        let remaining = self.remaining[player_index(player)];
+        match self.running {
+            Some((running_player, started)) if running_player == player => {
+                remaining.saturating_sub(self.source.now().saturating_sub(started))
+            }
+            _ => remaining,
+        }
+    }
+
+    /// Returns the player who has run out of time, if any.
+    pub fn flagged_player(&self) -> Option<Player> {
+        [Player::Circle, Player::Cross]
+            .into_iter()
+            .find(|&player| self.remaining(player).is_zero())
+    }
+
+    /// Starts the clock of the given player, stopping the other player's clock first if it's
+    /// running.
+    pub fn start(&mut self, player: Player) {
+        self.stop();
+        self.running = Some((player, self.source.now()));
+    }
+
+    /// Stops the running clock, charging its player for the time elapsed since it started.
+    pub fn stop(&mut self) {
+        if let Some((player, _)) = self.running {
+            self.remaining[player_index(player)] = self.remaining(player);
+            self.running = None;
+        }
+    }
+
+    /// Ends the turn of the player whose clock is running and starts the other player's clock.
+    ///
+    /// The player who ends their turn gets the increment or their time for the next play
+    /// according to the time control, unless they've run out of time, in which case the clock
+    /// stops and this returns a [`FlagFall`]. Does nothing if no clock is running.
+    pub fn press(&mut self) -> Result<(), FlagFall> {
+        let Some(player) = self.running_player() else {
+            return Ok(());
+        };
+        self.stop();
+
+        let remaining = &mut self.remaining[player_index(player)];
+        if remaining.is_zero() {
+            return Err(FlagFall { player });
+        }
+        match self.time_control {
+            TimeControl::SuddenDeath { .. } => (),
+            TimeControl::Increment { increment, .. } => *remaining += increment,
+            TimeControl::PerMove { per_move } => *remaining = per_move,
+        }
+
+        self.start(player.other());
+        Ok(())
+    }
+}
+
+/// The state of a [`Clock`] at some point in time, without its time source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClockState {
+    pub time_control: TimeControl,
+    pub circle_remaining: Duration,
+    pub cross_remaining: Duration,
+    /// The player whose clock is running, if any.
+    pub running_player: Option<Player>,
+}
+
+impl<S: TimeSource> From<Clock<S>> for ClockState {
+    fn from(clock: Clock<S>) -> Self {
+        clock.state()
+    }
+}
+
+/// The error returned when a player runs out of time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagFall {
+    /// The player who ran out of time.
+    pub player: Player,
+}
+
+impl Display for FlagFall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} has run out of time", self.player)
+    }
+}
+
+impl Error for FlagFall {}
+
+impl Game {
+    /// Returns the outcome of this game when the given player runs out of time.
+    ///
+    /// The other player wins if they can still possibly win the game, and the game is drawn
+    /// otherwise. See [`Game::can_still_win`].
+    pub fn timeout_outcome(&self, flagged_player: Player) -> BoardOutcome {
+        let opponent = flagged_player.other();
+        if self.can_still_win(opponent) {
+            BoardOutcome::WonBy(opponent)
+        } else {
+            BoardOutcome::Draw
+        }
+    }
+
+    /// Check if there's any way the given player could still win this game, however the players
+    /// play from here.
+    ///
+    /// In progress, that's the case if the player who would win by completing three regions in a
+    /// line still can: there's a line of regions each of which either already counts for them or
+    /// is in progress with a line of tiles the other player hasn't marked. With
+    /// [`RuleSet::region_count_tiebreak`](crate::RuleSet::region_count_tiebreak), any game in
+    /// progress may still be won on the region count, so it's always the case.
+    pub fn can_still_win(&self, player: Player) -> bool {
+        match self.state {
+            BoardState::Complete(BoardOutcome::WonBy(winner)) => winner == player,
+            BoardState::Complete(BoardOutcome::Draw) => false,
+            BoardState::InProgress => {
+                // The line winner mapping is its own inverse, so it also maps a winner to the
+                // player who has to complete the line for them to win.
+                let line_player = self.rules.mode.line_winner(player);
+                let blocker = line_player.other();
+                self.rules.region_count_tiebreak
+                    || self.board.has_line(|region| {
+                        self.rules.is_region_marked_by(region, line_player)
+                            || region.is_markable()
+                                && BoardIndex::ALL_LINES.iter().any(|line| {
+                                    line.iter()
+                                        .all(|&index| region.board[index] != Tile::Marked(blocker))
+                                })
+                    })
+            }
+        }
+    }
+}
+
+fn player_index(player: Player) -> usize {
+    match player {
+        Player::Circle => 0,
+        Player::Cross => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{advance, fake_clock};
+
+    #[test]
+    fn increment() {
+        let (mut clock, time) = fake_clock(TimeControl::Increment {
+            total: Duration::from_secs(60),
+            increment: Duration::from_secs(5),
+        });
+        clock.start(Player::Circle);
+        advance(&time, 10);
+        assert_eq!(clock.remaining(Player::Circle), Duration::from_secs(50));
+        assert_eq!(clock.press(), Ok(()));
+        assert_eq!(clock.remaining(Player::Circle), Duration::from_secs(55));
+        assert_eq!(clock.running_player(), Some(Player::Cross));
+
+        advance(&time, 20);
+        assert_eq!(clock.remaining(Player::Circle), Duration::from_secs(55));
+        assert_eq!(clock.remaining(Player::Cross), Duration::from_secs(40));
+        assert_eq!(clock.press(), Ok(()));
+        assert_eq!(clock.remaining(Player::Cross), Duration::from_secs(45));
+        assert_eq!(clock.running_player(), Some(Player::Circle));
+    }
+
+    #[test]
+    fn per_move() {
+        let (mut clock, time) = fake_clock(TimeControl::PerMove {
+            per_move: Duration::from_secs(30),
+        });
+        clock.start(Player::Circle);
+        advance(&time, 10);
+        assert_eq!(clock.press(), Ok(()));
+        assert_eq!(clock.remaining(Player::Circle), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn flag_fall() {
+        let (mut clock, time) = fake_clock(TimeControl::SuddenDeath {
+            total: Duration::from_secs(60),
+        });
+        clock.start(Player::Circle);
+        advance(&time, 59);
+        assert_eq!(clock.flagged_player(), None);
+        advance(&time, 2);
+        assert_eq!(clock.remaining(Player::Circle), Duration::ZERO);
+        assert_eq!(clock.flagged_player(), Some(Player::Circle));
+        assert_eq!(
+            clock.press(),
+            Err(FlagFall {
+                player: Player::Circle
+            })
+        );
+        assert_eq!(clock.running_player(), None);
+        assert_eq!(clock.flagged_player(), Some(Player::Circle));
+        assert_eq!(clock.remaining(Player::Cross), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn pause() {
+        let (mut clock, time) = fake_clock(TimeControl::SuddenDeath {
+            total: Duration::from_secs(60),
+        });
+        assert_eq!(clock.press(), Ok(()));
+        assert_eq!(clock.running_player(), None);
+
+        clock.start(Player::Cross);
+        advance(&time, 15);
+        clock.stop();
+        assert_eq!(clock.running_player(), None);
+        advance(&time, 100);
+        assert_eq!(clock.remaining(Player::Cross), Duration::from_secs(45));
+        assert_eq!(clock.flagged_player(), None);
+
+        clock.start(Player::Cross);
+        advance(&time, 5);
+        assert_eq!(clock.remaining(Player::Cross), Duration::from_secs(40));
+        assert_eq!(clock.remaining(Player::Circle), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let (mut clock, time) = fake_clock(TimeControl::SuddenDeath {
+            total: Duration::from_secs(60),
+        });
+        clock.start(Player::Circle);
+        advance(&time, 10);
+        assert_eq!(clock.press(), Ok(()));
+        advance(&time, 20);
+        let state = clock.state();
+        assert_eq!(
+            state,
+            ClockState {
+                time_control: clock.time_control(),
+                circle_remaining: Duration::from_secs(50),
+                cross_remaining: Duration::from_secs(40),
+                running_player: Some(Player::Cross),
+            }
+        );
+
+        let (loaded, time) = fake_clock(clock.time_control());
+        let loaded = Clock::from_state(state, loaded.source);
+        assert_eq!(loaded.state(), state);
+        advance(&time, 5);
+        assert_eq!(loaded.remaining(Player::Cross), Duration::from_secs(35));
+        assert_eq!(loaded.remaining(Player::Circle), Duration::from_secs(50));
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    fmt::{self, Display, Write},
+    str::FromStr,
+};
+
+use crate::{
+    notation::play_at,
+    position::{
+        parse_index_list, parse_player, player_char, set_cross_won_regions, write_index_list,
+        ParsePositionError,
+    },
+    Board, BoardIndex, BoardOutcome, BoardState, Game, Player, Region, RuleSet, Tile,
+};
+
+/// The character of an unmarked tile.
+const UNMARKED: char = '.';
+/// The character of an unmarked tile in the region the player to move is forced to play in.
+const FORCED: char = '*';
+
+impl Display for Board<Tile> {
+    /// Draws the board as 3 lines of 3 tiles separated by spaces, where `o` and `x` are marked
+    /// tiles and `.` is an unmarked tile.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_board(f, self, UNMARKED)
+    }
+}
+
+impl Display for Region {
+    /// Draws the region like a [`Board<Tile>`], but with the unmarked tiles of a region won by a
+    /// player drawn as `O` or `X`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_board(f, &self.board, unmarked_char(self, false))
+    }
+}
+
+fn write_board(f: &mut fmt::Formatter<'_>, board: &Board<Tile>, unmarked: char) -> fmt::Result {
+    for row in 0..3 {
+        if row > 0 {
+            f.write_char('\n')?;
+        }
+        for column in 0..3 {
+            if column > 0 {
+                f.write_char(' ')?;
+            }
+            let index = BoardIndex::from_row_column(row, column)
+                .expect("rows and columns below 3 should always be valid.");
+            f.write_char(tile_char(board[index], unmarked))?;
+        }
+    }
+    Ok(())
+}
+
+impl Display for Game {
+    /// Draws the game as a diagram of the whole 9x9 grid followed by a status line, e.g.
+    ///
+    /// ```text
+    /// . . . | . . . | . . .
+    /// . . . | . . . | . . .
+    /// . . . | . . . | . . .
+    /// ------+-------+------
+    /// . . . | * * * | . . .
+    /// . . . | * o * | . . .
+    /// . . . | * * * | . . .
+    /// ------+-------+------
+    /// . . . | . . . | . . .
+    /// . . . | . . . | . . .
+    /// . . . | . . . | . . .
+    /// x to move, previous index b2
+    /// ```
+    ///
+    /// Tiles are drawn as `o` and `x` when marked and `.` when unmarked. Unmarked tiles are drawn
+    /// as `O` or `X` instead in a region won by that player, and as `*` in the region the player
+    /// to move is forced to play in.
+    ///
+    /// The status line starts with the player to move, or the outcome once the game is over,
+    /// followed by the previous play index if there is one, then the rules and the regions with a
+    /// three-in-a-line of both players won by Cross, written like the optional fields of
+    /// [`Game::to_position_string`] and left out in the same cases. The alternate form (`{:#}`) draws the
+    /// separators with box-drawing characters instead.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (vertical, horizontal) = if f.alternate() {
+            ("│", "──────┼───────┼──────")
+        } else {
+            ("|", "------+-------+------")
+        };
+
+        let forced_region_index = match self.state {
+            BoardState::InProgress => self.allowed_region_index(),
+            BoardState::Complete(_) => None,
+        };
+        for row in 0..9 {
+            if row > 0 && row % 3 == 0 {
+                writeln!(f, "{horizontal}")?;
+            }
+            for column in 0..9 {
+                if column > 0 && column % 3 == 0 {
+                    write!(f, " {vertical} ")?;
+                } else if column > 0 {
+                    f.write_char(' ')?;
+                }
+                let (region_index, tile_index) =
+                    play_at(row, column).expect("rows and columns below 9 should always be valid.");
+                let region = &self.board[region_index];
+                let is_forced = forced_region_index == Some(region_index);
+                let tile = region.board[tile_index];
+                f.write_char(tile_char(tile, unmarked_char(region, is_forced)))?;
+            }
+            f.write_char('\n')?;
+        }
+
+        match self.state {
+            BoardState::InProgress => write!(f, "{} to move", player_char(self.current_player))?,
+            BoardState::Complete(BoardOutcome::WonBy(player)) => {
+                write!(f, "{} won", player_char(player))?
+            }
+            BoardState::Complete(BoardOutcome::Draw) => f.write_str("draw")?,
+        }
+        if let Some(index) = self.previous_play_index {
+            write!(f, ", previous index {index}")?;
+        }
+        let cross_won_regions = self.cross_won_regions_of_both();
+        if self.rules != RuleSet::default() || !cross_won_regions.is_empty() {
+            write!(f, ", rules {}", self.rules)?;
+        }
+        if !cross_won_regions.is_empty() {
+            f.write_str(", won by x ")?;
+            write_index_list(f, &cross_won_regions)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParsePositionError;
+
+    /// Parses a diagram as drawn by the [`Display`] implementation of [`Game`], in either form.
+    ///
+    /// Empty lines and spaces around lines are ignored, and `.`, `*`, `O` and `X` are all read as
+    /// unmarked tiles. Like [`Game::from_position_string`], the states of the regions and the
+    /// game are recomputed from the tiles with the given rules, or the default ones if they're left
+    /// out, and positions that can't be reached in a game are rejected, including ones whose
+    /// status line doesn't match the tiles.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.lines().map(str::trim).filter(|s| !s.is_empty()).collect();
+        let [grid @ .., status] = &lines[..] else {
+            return Err(ParsePositionError::InvalidTiles(s.to_owned()));
+        };
+        if grid.len() != 11 {
+            return Err(ParsePositionError::InvalidTiles(s.to_owned()));
+        }
+
+        let mut board = Board::<Region>::default();
+        let mut n_marks = 0;
+        for (row, line) in grid
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i % 4 != 3)
+            .map(|(i, line)| (i - i / 4, line))
+        {
+            let invalid_row = || ParsePositionError::InvalidTiles((*line).to_owned());
+            let tiles: Vec<char> = line
+                .chars()
+                .filter(|&c| !matches!(c, ' ' | '|' | '│'))
+                .collect();
+            if tiles.len() != 9 {
+                return Err(invalid_row());
+            }
+            for (column, c) in tiles.into_iter().enumerate() {
+                let tile = match c {
+                    UNMARKED | FORCED | 'O' | 'X' => Tile::Unmarked,
+                    c => Tile::Marked(parse_player(c).ok_or_else(invalid_row)?),
+                };
+                if matches!(tile, Tile::Marked(_)) {
+                    n_marks += 1;
+                }
+                let (region_index, tile_index) =
+                    play_at(row, column).expect("rows and columns below 9 should always be valid.");
+                board[region_index].board[tile_index] = tile;
+            }
+        }
+        for separator in [grid[3], grid[7]] {
+            if !separator
+                .chars()
+                .all(|c| matches!(c, '-' | '+' | '─' | '┼'))
+            {
+                return Err(ParsePositionError::InvalidTiles(separator.to_owned()));
+            }
+        }
+
+        let invalid_status = || ParsePositionError::InvalidStatus((*status).to_owned());
+        let mut parts = status.split(", ");
+        let outcome = parts.next().ok_or_else(invalid_status)?;
+        let mut part = parts.next();
+        let mut optional_part = |prefix: &str| {
+            let value = part?.strip_prefix(prefix)?;
+            part = parts.next();
+            Some(value)
+        };
+        let previous_play_index = match optional_part("previous index ") {
+            Some(index) => Some(index.parse().map_err(|_| invalid_status())?),
+            None => None,
+        };
+        let rules = match optional_part("rules ") {
+            Some(rules) => rules.parse().map_err(|_| invalid_status())?,
+            None => RuleSet::default(),
+        };
+        if let Some(regions) = optional_part("won by x ") {
+            let regions = parse_index_list(regions).ok_or_else(invalid_status)?;
+            set_cross_won_regions(&mut board, &regions).ok_or_else(invalid_status)?;
+        }
+        if part.is_some() {
+            return Err(invalid_status());
+        }
+        // The player to move after a finished game is the one who didn't make the last play.
+        let player_after_marks = match n_marks % 2 {
+            0 => Player::Circle,
+            _ => Player::Cross,
+        };
+        let (current_player, state) = match outcome.split_once(' ') {
+            Some((player, "to move")) => (
+                parse_single_player(player).ok_or_else(invalid_status)?,
+                None,
+            ),
+            Some((player, "won")) => {
+                let player = parse_single_player(player).ok_or_else(invalid_status)?;
+                (player_after_marks, Some(BoardOutcome::WonBy(player)))
+            }
+            None if outcome == "draw" => (player_after_marks, Some(BoardOutcome::Draw)),
+            _ => return Err(invalid_status()),
+        };
+
+        let game = Self::from_tiles(board, current_player, previous_play_index, rules)?;
+        if game.state != state.map_or(BoardState::InProgress, BoardState::Complete) {
+            return Err(invalid_status());
+        }
+        Ok(game)
+    }
+}
+
+/// Returns the character of the given tile, where `unmarked` is the character of an unmarked
+/// tile.
+fn tile_char(tile: Tile, unmarked: char) -> char {
+    match tile {
+        Tile::Marked(player) => player_char(player),
+        Tile::Unmarked => unmarked,
+    }
+}
+
+/// Returns the character of the unmarked tiles in the given region.
+fn unmarked_char(region: &Region, is_forced: bool) -> char {
+    match region.state {
+        _ if is_forced => FORCED,
+        BoardState::Complete(BoardOutcome::WonBy(player)) => {
+            player_char(player).to_ascii_uppercase()
+        }
+        _ => UNMARKED,
+    }
+}
+
+fn parse_single_player(s: &str) -> Option<Player> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => parse_player(c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{random_positions, rule_sets},
+        GameMode,
+    };
+
+    #[test]
+    fn diagram_round_trip() {
+        for rules in rule_sets() {
+            for seed in 0..20 {
+                for game in random_positions(rules, seed) {
+                    for diagram in [format!("{game}"), format!("{game:#}")] {
+                        assert_eq!(diagram.parse(), Ok(game.clone()), "\n{diagram}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn status_with_rules() {
+        let mut game = Game::from_position_string("9/9/9/9/4o4/9/9/9/9 x b2").unwrap();
+        game.rules.mode = GameMode::Misere;
+        let diagram = game.to_string();
+        assert!(
+            diagram.ends_with("\nx to move, previous index b2, rules misere"),
+            "{diagram}"
+        );
+        assert_eq!(diagram.parse(), Ok(game));
+
+        for status in [
+            "x to move, rules misere, previous index b2",
+            "x to move, previous index b2, rules normal",
+            "x to move, previous index b2, won by x b2",
+            "x to move, previous index b2, rules misere, won by x",
+        ] {
+            let diagram = diagram.replace("x to move, previous index b2, rules misere", status);
+            assert_eq!(
+                diagram.parse::<Game>(),
+                Err(ParsePositionError::InvalidStatus(status.to_owned())),
+                "{status:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn diagram_snapshot() {
+        let game = Game::from_position_string("ooo6/xx7/9/9/9/9/9/9/9 x c1").unwrap();
+        let diagram = "\
+o o o | . . . | * * *
+x x O | . . . | * * *
+O O O | . . . | * * *
+------+-------+------
+. . . | . . . | . . .
+. . . | . . . | . . .
+. . . | . . . | . . .
+------+-------+------
+. . . | . . . | . . .
+. . . | . . . | . . .
+. . . | . . . | . . .
+x to move, previous index c1";
+        assert_eq!(game.to_string(), diagram);
+        assert_eq!(
+            game.board[BoardIndex::UpperLeft].to_string(),
+            "o o o\nx x O\nO O O"
+        );
+        assert_eq!(
+            game.board[BoardIndex::UpperLeft].board.to_string(),
+            "o o o\nx x .\n. . ."
+        );
+        assert_eq!(diagram.parse(), Ok(game));
+    }
+}
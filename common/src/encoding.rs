@@ -0,0 +1,252 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    position::ParsePositionError, Board, BoardIndex, BoardOutcome, BoardState, Game, Player,
+    Region, RuleSet, Tile,
+};
+
+/// The number of bytes in the binary encoding of a [`Game`].
+pub const ENCODED_LEN: usize = 19;
+
+/// The number of possible values of the tiles of a region, where each tile is unmarked, marked by
+/// Circle or marked by Cross.
+const REGION_RADIX: u32 = 19683;
+/// The number of possible values of the previous play index: none, or one of the board indices.
+const PREVIOUS_PLAY_INDEX_RADIX: u32 = BoardIndex::N as u32 + 1;
+/// The number of possible values of the winners of the regions with a three-in-a-line of both
+/// players: one bit for each region.
+const WINNERS_RADIX: u32 = 1 << BoardIndex::N;
+/// The number of possible values of the rules, as returned by [`RuleSet::to_bits`].
+const RULES_RADIX: u32 = 1 << RuleSet::NAMES.len();
+
+impl Game {
+    /// Returns the binary encoding of this game's position in [`ENCODED_LEN`] bytes.
+    ///
+    /// The tiles of each region are read as a base-3 number, with unmarked as 0, Circle as 1 and
+    /// Cross as 2, from the upper left tile as the least significant digit. The regions in
+    /// [`BoardIndex`] order and the previous play index (0 for none, or 1 more than the index)
+    /// then make a mixed-radix number, stored in little-endian order, followed by two sets of
+    /// bits:
+    ///
+    /// - the winners of the regions with a three-in-a-line of both players, which can only happen
+    ///   under [`RuleSet::won_regions_playable`], with a bit set for each of those regions won by
+    ///   Cross, from the upper left region as the least significant bit,
+    /// - the rules, as returned by [`RuleSet::to_bits`].
+    ///
+    /// The player to move and the states of the other regions and of the game follow from the
+    /// tiles and the rules, so they aren't stored.
+    pub fn to_bytes(&self) -> [u8; ENCODED_LEN] {
+        let mut bytes = [0; ENCODED_LEN];
+        for region in &self.board.tiles {
+            let digit = region.board.tiles.iter().rev().fold(0, |value, tile| {
+                value * 3
+                    + match tile {
+                        Tile::Unmarked => 0,
+                        Tile::Marked(Player::Circle) => 1,
+                        Tile::Marked(Player::Cross) => 2,
+                    }
+            });
+            multiply_add(&mut bytes, REGION_RADIX, digit);
+        }
+        let previous_play_index = self
+            .previous_play_index
+            .map_or(0, |index| usize::from(index) as u32 + 1);
+        multiply_add(&mut bytes, PREVIOUS_PLAY_INDEX_RADIX, previous_play_index);
+        let winners = self.board.tiles.iter().rev().fold(0, |winners, region| {
+            let is_won_by_cross = region.has_lines_of_both()
+                && region.state == BoardState::Complete(BoardOutcome::WonBy(Player::Cross));
+            winners << 1 | u32::from(is_won_by_cross)
+        });
+        multiply_add(&mut bytes, WINNERS_RADIX, winners);
+        multiply_add(&mut bytes, RULES_RADIX, self.rules.to_bits());
+        bytes
+    }
+
+    /// Decodes a game encoded by [`Game::to_bytes`].
+    ///
+    /// Like [`Game::from_position_string`], the states of the regions and the game are
+    /// recomputed from the tiles with the encoded rules, and positions that can't be reached in a
+    /// game are rejected. Bytes that aren't the encoding of any position, because they're too
+    /// large or give a winner to a region without a three-in-a-line of both players, are rejected
+    /// with [`ParsePositionError::InvalidEncoding`].
+    pub fn from_bytes(bytes: &[u8; ENCODED_LEN]) -> Result<Self, ParsePositionError> {
+        let mut bytes = *bytes;
+        let rules = RuleSet::from_bits(divide(&mut bytes, RULES_RADIX));
+        let mut winners = divide(&mut bytes, WINNERS_RADIX);
+        let previous_play_index = match divide(&mut bytes, PREVIOUS_PLAY_INDEX_RADIX) {
+            0 => None,
+            index => Some(
+                BoardIndex::try_from(index as usize - 1)
+                    .expect("a remainder below 10 should always be 0 or 1 more than an index."),
+            ),
+        };
+
+        let mut board = Board::<Region>::default();
+        let (mut n_circle, mut n_cross) = (0, 0);
+        for region in board.tiles.iter_mut().rev() {
+            let mut digit = divide(&mut bytes, REGION_RADIX);
+            for tile in &mut region.board.tiles {
+                *tile = match digit % 3 {
+                    0 => Tile::Unmarked,
+                    1 => {
+                        n_circle += 1;
+                        Tile::Marked(Player::Circle)
+                    }
+                    _ => {
+                        n_cross += 1;
+                        Tile::Marked(Player::Cross)
+                    }
+                };
+                digit /= 3;
+            }
+        }
+        if bytes.iter().any(|&byte| byte != 0) {
+            return Err(ParsePositionError::InvalidEncoding);
+        }
+        for region in &mut board.tiles {
+            let is_won_by_cross = winners & 1 == 1;
+            winners >>= 1;
+            if region.has_lines_of_both() {
+                let winner = if is_won_by_cross {
+                    Player::Cross
+                } else {
+                    Player::Circle
+                };
+                region.state = BoardState::Complete(BoardOutcome::WonBy(winner));
+            } else if is_won_by_cross {
+                return Err(ParsePositionError::InvalidEncoding);
+            }
+        }
+
+        let current_player = if n_circle > n_cross {
+            Player::Cross
+        } else {
+            Player::Circle
+        };
+        Self::from_tiles(board, current_player, previous_play_index, rules)
+    }
+
+    /// Returns the [`PositionId`] of this game.
+    pub fn position_id(&self) -> PositionId {
+        PositionId(self.to_bytes())
+    }
+}
+
+/// Multiplies the little-endian number in `bytes` by `radix` and adds `digit` to it.
+fn multiply_add(bytes: &mut [u8; ENCODED_LEN], radix: u32, digit: u32) {
+    let mut carry = digit;
+    for byte in bytes.iter_mut() {
+        let value = u32::from(*byte) * radix + carry;
+        *byte = value as u8;
+        carry = value >> 8;
+    }
+    assert_eq!(
+        carry, 0,
+        "an encoded game should always fit in {ENCODED_LEN} bytes."
+    );
+}
+
+/// Divides the little-endian number in `bytes` by `radix` and returns the remainder.
+fn divide(bytes: &mut [u8; ENCODED_LEN], radix: u32) -> u32 {
+    let mut remainder = 0;
+    for byte in bytes.iter_mut().rev() {
+        let value = (remainder << 8) | u32::from(*byte);
+        *byte = (value / radix) as u8;
+        remainder = value % radix;
+    }
+    remainder
+}
+
+/// A compact identifier of a position, which is its binary encoding as returned by
+/// [`Game::to_bytes`].
+///
+/// Equal positions have equal ids, so ids can be used as keys in place of whole games. They're
+/// written as hexadecimal.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct PositionId(pub [u8; ENCODED_LEN]);
+
+impl Display for PositionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0
+            .iter()
+            .rev()
+            .try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl TryFrom<PositionId> for Game {
+    type Error = ParsePositionError;
+
+    fn try_from(id: PositionId) -> Result<Self, Self::Error> {
+        Self::from_bytes(&id.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{random_positions, rule_sets};
+
+    /// Returns the encoding of the given digits, from the most significant one.
+    fn encode(digits: &[(u32, u32)]) -> [u8; ENCODED_LEN] {
+        let mut bytes = [0; ENCODED_LEN];
+        for &(radix, digit) in digits {
+            multiply_add(&mut bytes, radix, digit);
+        }
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        for rules in rule_sets() {
+            for seed in 0..10 {
+                for game in random_positions(rules, seed) {
+                    let decoded = Game::from_bytes(&game.to_bytes());
+                    assert_eq!(decoded.as_ref(), Ok(&game), "{rules:?}\n{game}");
+                    assert_eq!(decoded.unwrap().key(), game.key());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn too_large() {
+        let mut max = [(REGION_RADIX, REGION_RADIX - 1); BoardIndex::N].to_vec();
+        max.extend([
+            (PREVIOUS_PLAY_INDEX_RADIX, PREVIOUS_PLAY_INDEX_RADIX - 1),
+            (WINNERS_RADIX, WINNERS_RADIX - 1),
+            (RULES_RADIX, RULES_RADIX - 1),
+        ]);
+        let mut past_max = encode(&max);
+        multiply_add(&mut past_max, 1, 1);
+        for bytes in [past_max, [u8::MAX; ENCODED_LEN]] {
+            assert_eq!(
+                Game::from_bytes(&bytes),
+                Err(ParsePositionError::InvalidEncoding)
+            );
+        }
+    }
+
+    #[test]
+    fn winner_without_lines_of_both() {
+        let empty = [(REGION_RADIX, 0); BoardIndex::N];
+        let bytes = |winners| {
+            let mut digits = empty.to_vec();
+            digits.extend([
+                (PREVIOUS_PLAY_INDEX_RADIX, 0),
+                (WINNERS_RADIX, winners),
+                (RULES_RADIX, 0),
+            ]);
+            encode(&digits)
+        };
+        assert_eq!(Game::from_bytes(&bytes(0)), Ok(Game::new()));
+        assert_eq!(
+            Game::from_bytes(&bytes(1)),
+            Err(ParsePositionError::InvalidEncoding)
+        );
+    }
+}
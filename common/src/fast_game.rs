@@ -0,0 +1,285 @@
+use rand::Rng;
+
+use crate::{
+    game::LegalMoves, Board, BoardIndex, BoardOutcome, BoardState, Game, GameMode, IsNoneOr,
+    MarkTileResult, Play, Player, Region, RuleSet, Tile,
+};
+
+/// A mask with one bit set for every tile in a region.
+const REGION_MASK: u16 = 0o777;
+
+/// Bit masks of [`BoardIndex::ALL_LINES`], one bit per tile in `usize::from(BoardIndex)` order.
+const LINE_MASKS: [u16; 8] = [
+    // Rows
+    0o007, 0o070, 0o700, // Columns
+    0o111, 0o222, 0o444, // Diagonals
+    0o421, 0o124,
+];
+
+/// `HAS_LINE[mask]` is `true` if the 9-bit `mask` contains at least one three-in-a-line.
+const HAS_LINE: [bool; 512] = {
+    let mut table = [false; 512];
+    let mut mask = 0;
+    while mask < 512 {
+        let mut i = 0;
+        while i < LINE_MASKS.len() {
+            if mask as u16 & LINE_MASKS[i] == LINE_MASKS[i] {
+                table[mask] = true;
+            }
+            i += 1;
+        }
+        mask += 1;
+    }
+    table
+};
+
+/// A packed representation of [`Game`] for fast move generation.
+///
+/// Every tile is a bit at position `9 * region + tile` in a per-player occupancy mask, and
+/// every region is a bit in the region state masks, so marking a tile and checking for
+/// three-in-a-line are a handful of bitwise operations and table lookups instead of scans over
+/// [`BoardIndex::ALL_LINES`].
+///
+/// Convert from and to [`Game`] with [`From`]. Only the rule sets for which
+/// [`FastGame::supports`] returns `true` are supported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FastGame {
+    /// The tiles marked by each player, indexed by [`player_index`].
+    tiles: [u128; 2],
+    /// The regions won by each player, indexed by [`player_index`].
+    regions_won: [u16; 2],
+    /// The regions that have been won or drawn.
+    regions_complete: u16,
+    state: BoardState,
+    current_player: Player,
+    previous_play_index: Option<BoardIndex>,
+    mode: GameMode,
+}
+
+impl FastGame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if a game with the given rules can be converted into a [`FastGame`], which is the
+    /// case for the default rules in either [`GameMode`].
+    pub fn supports(rules: &RuleSet) -> bool {
+        *rules
+            == RuleSet {
+                mode: rules.mode,
+                ..Default::default()
+            }
+    }
+
+    pub fn state(&self) -> BoardState {
+        self.state
+    }
+
+    pub fn current_player(&self) -> Player {
+        self.current_player
+    }
+
+    pub fn previous_play_index(&self) -> Option<BoardIndex> {
+        self.previous_play_index
+    }
+
+    /// Get the index of the region that the current player have to to play in.
+    /// If it's `None`, that means the player can play in any region.
+    pub fn allowed_region_index(&self) -> Option<BoardIndex> {
+        let previous_play_index = self.previous_play_index?;
+        self.is_region_open(usize::from(previous_play_index))
+            .then_some(previous_play_index)
+    }
+
+    pub fn is_region_enabled(&self, index: BoardIndex) -> bool {
+        matches!(self.state, BoardState::InProgress)
+            && self.allowed_region_index().my_is_none_or(|i| i == index)
+    }
+
+    /// Returns an iterator over all the valid plays for the current player, in region then tile
+    /// order, the same as [`Game::legal_moves`].
+    pub fn legal_moves(&self) -> LegalMoves {
+        LegalMoves::from_mask(self.legal_mask())
+    }
+
+    /// Check if the given play is valid for the current player.
+    pub fn is_legal(&self, play: Play) -> bool {
+        self.legal_mask() & LegalMoves::bit(play) != 0
+    }
+
+    pub fn mark_tile(&mut self, (region_index, tile_index): Play) -> MarkTileResult {
+        let bit = LegalMoves::bit((region_index, tile_index));
+        if self.legal_mask() & bit == 0 {
+            return MarkTileResult::NoChange;
+        }
+
+        let region = usize::from(region_index);
+        let player = player_index(self.current_player);
+        self.tiles[player] |= bit;
+
+        let region_bit = 1 << region;
+        let result = if HAS_LINE[usize::from(self.region_tiles(player, region))] {
+            self.regions_won[player] |= region_bit;
+            self.regions_complete |= region_bit;
+            if HAS_LINE[usize::from(self.regions_won[player])] {
+                self.decide(BoardOutcome::WonBy(
+                    self.mode.line_winner(self.current_player),
+                ))
+            } else {
+                self.check_filled()
+            }
+        } else if self.region_tiles(0, region) | self.region_tiles(1, region) == REGION_MASK {
+            self.regions_complete |= region_bit;
+            self.check_filled()
+        } else {
+            MarkTileResult::TileMarked
+        };
+
+        self.current_player = self.current_player.other();
+        self.previous_play_index = Some(tile_index);
+
+        result
+    }
+
+    /// Returns a random valid play, choosing a region uniformly first and then a tile within it,
+    /// the same way [`crate::ai::random`] does for [`Game`].
+    ///
+    /// The caller must ensure the game is still in progress.
+    pub fn random_play(&self, rng: &mut impl Rng) -> Play {
+        assert!(matches!(self.state, BoardState::InProgress));
+
+        self.legal_moves()
+            .choose_by_region(rng)
+            .expect("an in-progress game should always have at least one possible play.")
+    }
+
+    /// Plays random moves until the game is over, then returns the outcome.
+    pub fn rollout(&mut self, rng: &mut impl Rng) -> BoardOutcome {
+        loop {
+            let play = self.random_play(rng);
+            if let MarkTileResult::OutcomeDecided(outcome) = self.mark_tile(play) {
+                return outcome;
+            }
+        }
+    }
+
+    /// Returns the mask of the valid plays, where each play is bit `9 * region + tile`.
+    fn legal_mask(&self) -> u128 {
+        if !matches!(self.state, BoardState::InProgress) {
+            return 0;
+        }
+        let regions = match self.allowed_region_index() {
+            Some(index) => 1 << usize::from(index),
+            None => !self.regions_complete & REGION_MASK,
+        };
+        let unmarked = !(self.tiles[0] | self.tiles[1]);
+        (0..BoardIndex::N)
+            .filter(|region| regions & (1 << region) != 0)
+            .fold(0, |mask, region| {
+                mask | (unmarked & (u128::from(REGION_MASK) << (9 * region)))
+            })
+    }
+
+    fn is_region_open(&self, region: usize) -> bool {
+        self.regions_complete & (1 << region) == 0
+    }
+
+    /// Returns the 9-bit mask of the tiles in the given region marked by the given player.
+    fn region_tiles(&self, player: usize, region: usize) -> u16 {
+        (self.tiles[player] >> (9 * region)) as u16 & REGION_MASK
+    }
+
+    fn check_filled(&mut self) -> MarkTileResult {
+        if self.regions_complete == REGION_MASK {
+            self.decide(BoardOutcome::Draw)
+        } else {
+            MarkTileResult::TileMarked
+        }
+    }
+
+    fn decide(&mut self, outcome: BoardOutcome) -> MarkTileResult {
+        self.state = BoardState::Complete(outcome);
+        MarkTileResult::OutcomeDecided(outcome)
+    }
+}
+
+impl From<&Game> for FastGame {
+    fn from(game: &Game) -> Self {
+        debug_assert!(
+            Self::supports(&game.rules),
+            "a fast game only supports the default rules."
+        );
+
+        let mut fast_game = Self {
+            state: game.state,
+            current_player: game.current_player,
+            previous_play_index: game.previous_play_index,
+            mode: game.rules.mode,
+            ..Default::default()
+        };
+
+        for (region_index, region) in game.board.enumerate() {
+            let offset = 9 * usize::from(region_index);
+            for (tile_index, tile) in region.board.enumerate() {
+                if let Tile::Marked(player) = tile {
+                    fast_game.tiles[player_index(*player)] |=
+                        1 << (offset + usize::from(tile_index));
+                }
+            }
+            if let BoardState::Complete(outcome) = region.state {
+                let region_bit = 1 << usize::from(region_index);
+                fast_game.regions_complete |= region_bit;
+                if let BoardOutcome::WonBy(player) = outcome {
+                    fast_game.regions_won[player_index(player)] |= region_bit;
+                }
+            }
+        }
+
+        fast_game
+    }
+}
+
+impl From<&FastGame> for Game {
+    fn from(fast_game: &FastGame) -> Self {
+        let mut board = Board::<Region>::default();
+        for region in 0..BoardIndex::N {
+            let mut tiles = Board::<Tile>::default();
+            for tile in 0..BoardIndex::N {
+                for player in [Player::Circle, Player::Cross] {
+                    if fast_game.region_tiles(player_index(player), region) & (1 << tile) != 0 {
+                        tiles[board_index(tile)] = Tile::Marked(player);
+                    }
+                }
+            }
+            board[board_index(region)] = Region {
+                board: tiles,
+                state: tiles.get_state(),
+            };
+        }
+
+        let mut game = Self {
+            board,
+            state: fast_game.state,
+            current_player: fast_game.current_player,
+            previous_play_index: fast_game.previous_play_index,
+            rules: RuleSet {
+                mode: fast_game.mode,
+                ..Default::default()
+            },
+            key: 0,
+        };
+        game.update_key();
+        game
+    }
+}
+
+fn player_index(player: Player) -> usize {
+    match player {
+        Player::Circle => 0,
+        Player::Cross => 1,
+    }
+}
+
+fn board_index(index: usize) -> BoardIndex {
+    BoardIndex::try_from(index).expect("bit positions within a region should always be below 9.")
+}
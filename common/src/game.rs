@@ -1,15 +1,33 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+};
+
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::{Board, BoardIndex, BoardItem, BoardState, IsNoneOr, MarkTileResult, Player, Region};
+use crate::{
+    zobrist::{player_key, previous_play_index_key, tile_key},
+    Board, BoardIndex, BoardItem, BoardOutcome, BoardState, InvalidGame, MarkTileResult, Player,
+    Region, RuleSet, Tile,
+};
 
 pub type Play = (BoardIndex, BoardIndex);
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Eq, Serialize, Deserialize)]
+#[serde(try_from = "GameFields")]
 pub struct Game {
     pub board: Board<Region>,
     pub state: BoardState,
     pub current_player: Player,
     pub previous_play_index: Option<BoardIndex>,
+    #[serde(default)]
+    pub rules: RuleSet,
+    /// The Zobrist key of the game. See [`Game::key`].
+    #[serde(skip)]
+    pub(crate) key: u64,
 }
 
 impl Game {
@@ -17,40 +35,409 @@ impl Game {
         Self::default()
     }
 
+    /// Returns a new game played with the given rules.
+    pub fn with_rules(rules: RuleSet) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
     /// Get the index of the region that the current player have to to play in.
     /// If it's `None`, that means the player can play in any region.
     pub fn allowed_region_index(&self) -> Option<BoardIndex> {
         let previous_play_index = self.previous_play_index?;
-        self.board[previous_play_index]
-            .is_markable()
+        self.rules
+            .is_region_open(&self.board[previous_play_index])
             .then_some(previous_play_index)
     }
 
+    /// Check if the current player can play in the given region under the rules of this game.
     pub fn is_region_enabled(&self, index: BoardIndex) -> bool {
-        matches!(self.state, BoardState::InProgress)
-            && self.allowed_region_index().my_is_none_or(|i| i == index)
+        self.check_region(index).is_ok()
+    }
+
+    /// Returns an iterator over all the valid plays for the current player, in region then tile
+    /// order.
+    ///
+    /// The iterator doesn't borrow the game, so the game can be modified while iterating.
+    pub fn legal_moves(&self) -> LegalMoves {
+        let mut plays = 0;
+        for (region_index, region) in self.board.enumerate() {
+            if self.check_region(region_index).is_ok() {
+                for (tile_index, _) in region.board.unmarked() {
+                    plays |= LegalMoves::bit((region_index, tile_index));
+                }
+            }
+        }
+        LegalMoves { plays }
+    }
+
+    /// Returns the number of valid plays for the current player.
+    pub fn legal_move_count(&self) -> usize {
+        self.legal_moves().len()
+    }
+
+    /// Check if the given play is valid for the current player.
+    ///
+    /// A play is valid if its region is enabled according to [`Game::is_region_enabled`] and its
+    /// tile is enabled according to [`Region::is_tile_enabled`] under the rules of this game.
+    pub fn is_legal(&self, play: Play) -> bool {
+        self.check_play(play).is_ok()
     }
 
-    pub fn mark_tile(&mut self, (region_index, tile_index): Play) -> MarkTileResult {
-        if !self.is_region_enabled(region_index) {
-            return MarkTileResult::NoChange;
+    /// Check if the given play is valid for the current player, and if not, why.
+    pub fn check_play(&self, (region_index, tile_index): Play) -> Result<(), IllegalMove> {
+        self.check_region(region_index)?;
+        if !self.board[region_index].board[tile_index].is_markable() {
+            return Err(IllegalMove::TileOccupied);
         }
+        Ok(())
+    }
+
+    /// Check if the current player can play in the given region, and if not, why.
+    fn check_region(&self, region_index: BoardIndex) -> Result<(), IllegalMove> {
+        if !matches!(self.state, BoardState::InProgress) {
+            return Err(IllegalMove::GameOver);
+        }
+        match self.allowed_region_index() {
+            Some(required) if required != region_index => {
+                return Err(IllegalMove::WrongRegion { required })
+            }
+            _ => (),
+        }
+        if !self.rules.is_region_open(&self.board[region_index]) {
+            return Err(IllegalMove::RegionClosed);
+        }
+        if self.rules.no_center_first_move
+            && self.previous_play_index.is_none()
+            && region_index == BoardIndex::Center
+        {
+            return Err(IllegalMove::CenterFirstMove);
+        }
+        Ok(())
+    }
+
+    /// Mark the given tile for the current player.
+    ///
+    /// This is a shorthand for [`Game::try_mark_tile`] for callers that don't need to know why a
+    /// play is invalid.
+    pub fn mark_tile(&mut self, play: Play) -> MarkTileResult {
+        match self.try_mark_tile(play) {
+            Ok(MoveOutcome::TileMarked | MoveOutcome::RegionDecided(_)) => {
+                MarkTileResult::TileMarked
+            }
+            Ok(MoveOutcome::GameDecided(outcome)) => MarkTileResult::OutcomeDecided(outcome),
+            Err(_) => MarkTileResult::NoChange,
+        }
+    }
+
+    /// Mark the given tile for the current player, then update the states of the region and the
+    /// game accordingly.
+    ///
+    /// Returns why the play is invalid, without changing the game, if it is.
+    pub fn try_mark_tile(
+        &mut self,
+        (region_index, tile_index): Play,
+    ) -> Result<MoveOutcome, IllegalMove> {
+        self.check_play((region_index, tile_index))?;
 
-        let result = match self.board[region_index].mark_tile(tile_index, self.current_player) {
-            MarkTileResult::NoChange => return MarkTileResult::NoChange,
-            MarkTileResult::TileMarked => MarkTileResult::TileMarked,
-            MarkTileResult::OutcomeDecided(_) => match self.board.get_state() {
-                BoardState::InProgress => MarkTileResult::TileMarked,
-                BoardState::Complete(outcome) => {
-                    self.state = BoardState::Complete(outcome);
-                    MarkTileResult::OutcomeDecided(outcome)
+        let region = &mut self.board[region_index];
+        let region_outcome = if matches!(region.state, BoardState::InProgress) {
+            match region.mark_tile(tile_index, self.current_player) {
+                MarkTileResult::NoChange => {
+                    unreachable!("a checked play should never result in NoChange.")
                 }
+                MarkTileResult::TileMarked => None,
+                MarkTileResult::OutcomeDecided(outcome) => Some(outcome),
+            }
+        } else {
+            // Playing in a region that has already been won doesn't change who won it, but it may
+            // fill the last open region.
+            region.board[tile_index] = Tile::Marked(self.current_player);
+            None
+        };
+
+        let outcome = match self.rules.get_state(&self.board, self.current_player) {
+            BoardState::Complete(outcome) => {
+                self.state = BoardState::Complete(outcome);
+                MoveOutcome::GameDecided(outcome)
+            }
+            BoardState::InProgress => match region_outcome {
+                Some(outcome) => MoveOutcome::RegionDecided(outcome),
+                None => MoveOutcome::TileMarked,
             },
         };
 
+        self.key ^= tile_key(self.current_player, (region_index, tile_index))
+            ^ player_key(self.current_player)
+            ^ player_key(self.current_player.other())
+            ^ previous_play_index_key(self.previous_play_index)
+            ^ previous_play_index_key(Some(tile_index));
         self.current_player = self.current_player.other();
         self.previous_play_index = Some(tile_index);
 
-        result
+        Ok(outcome)
+    }
+
+    /// Mark the given tile on behalf of the given player, which fails with
+    /// [`IllegalMove::NotYourTurn`] if it's not their turn.
+    ///
+    /// See also [`Game::try_mark_tile`].
+    pub fn try_mark_tile_as(
+        &mut self,
+        player: Player,
+        play: Play,
+    ) -> Result<MoveOutcome, IllegalMove> {
+        if matches!(self.state, BoardState::InProgress) && player != self.current_player {
+            return Err(IllegalMove::NotYourTurn(self.current_player));
+        }
+        self.try_mark_tile(play)
+    }
+
+    /// Revert the given play, which must be the last play made in this game.
+    ///
+    /// `previous_play_index` is the value [`Game::previous_play_index`] had before the play was
+    /// made, which is the tile index of the play before it, or `None` if it was the first play.
+    ///
+    /// Returns `false` and leaves the game unchanged if the given play couldn't have been the last
+    /// play.
+    pub fn unmark_tile(
+        &mut self,
+        (region_index, tile_index): Play,
+        previous_play_index: Option<BoardIndex>,
+    ) -> bool {
+        let last_player = self.current_player.other();
+        let region = &mut self.board[region_index];
+        if self.previous_play_index != Some(tile_index)
+            || region.board[tile_index] != Tile::Marked(last_player)
+        {
+            return false;
+        }
+
+        // A play can only be made in a game that is in progress, and in a region that is either in
+        // progress or already won, in which case it still is unless this play won it.
+        region.board[tile_index] = Tile::Unmarked;
+        region.state = match region.state {
+            BoardState::Complete(BoardOutcome::WonBy(winner))
+                if region.board.check_player(winner) =>
+            {
+                region.state
+            }
+            _ => BoardState::InProgress,
+        };
+        self.state = BoardState::InProgress;
+        self.key ^= tile_key(last_player, (region_index, tile_index))
+            ^ player_key(last_player)
+            ^ player_key(self.current_player)
+            ^ previous_play_index_key(self.previous_play_index)
+            ^ previous_play_index_key(previous_play_index);
+        self.current_player = last_player;
+        self.previous_play_index = previous_play_index;
+
+        true
+    }
+}
+
+impl PartialEq for Game {
+    /// Compares every field but the Zobrist key, which follows from the others as long as it's
+    /// kept up to date.
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.state == other.state
+            && self.current_player == other.current_player
+            && self.previous_play_index == other.previous_play_index
+            && self.rules == other.rules
+    }
+}
+
+impl Hash for Game {
+    /// Hashes the Zobrist key of the game only, which is much cheaper than hashing every field.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// The serialized fields of a [`Game`], which are validated and from which its Zobrist key is
+/// recomputed when deserializing.
+#[derive(Deserialize)]
+struct GameFields {
+    board: Board<Region>,
+    state: BoardState,
+    current_player: Player,
+    previous_play_index: Option<BoardIndex>,
+    #[serde(default)]
+    rules: RuleSet,
+}
+
+impl TryFrom<GameFields> for Game {
+    type Error = InvalidGame;
+
+    fn try_from(fields: GameFields) -> Result<Self, Self::Error> {
+        let mut game = Self {
+            board: fields.board,
+            state: fields.state,
+            current_player: fields.current_player,
+            previous_play_index: fields.previous_play_index,
+            rules: fields.rules,
+            key: 0,
+        };
+        game.update_key();
+        game.validate()?;
+        Ok(game)
+    }
+}
+
+/// The result of a valid play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveOutcome {
+    /// A tile has been marked, but the outcome of its region has not been decided yet.
+    TileMarked,
+    /// A tile has been marked, and the outcome of its region has been decided, but the outcome of
+    /// the game has not.
+    RegionDecided(BoardOutcome),
+    /// A tile has been marked, and the outcome of the game has been decided.
+    GameDecided(BoardOutcome),
+}
+
+/// The reason a play is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMove {
+    /// The game is already over.
+    GameOver,
+    /// The play has to be made in the `required` region.
+    WrongRegion { required: BoardIndex },
+    /// The region of the play has already been won or drawn.
+    RegionClosed,
+    /// The tile of the play has already been marked.
+    TileOccupied,
+    /// The play was made by a player out of turn; it's the contained player's turn.
+    NotYourTurn(Player),
+    /// The first play of the game may not be in the center region under
+    /// [`RuleSet::no_center_first_move`].
+    CenterFirstMove,
+}
+
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GameOver => write!(f, "the game is already over"),
+            Self::WrongRegion { required } => write!(f, "the play has to be in region {required}"),
+            Self::RegionClosed => write!(f, "the region has already been decided"),
+            Self::TileOccupied => write!(f, "the tile has already been marked"),
+            Self::NotYourTurn(player) => write!(f, "it's {player}'s turn"),
+            Self::CenterFirstMove => write!(f, "the first play may not be in the center region"),
+        }
+    }
+}
+
+impl Error for IllegalMove {}
+
+/// An iterator over the valid plays of a game, created by [`Game::legal_moves`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LegalMoves {
+    /// The remaining plays, where each play is bit `9 * region + tile`.
+    plays: u128,
+}
+
+impl LegalMoves {
+    /// Returns the set of plays with only the given play.
+    pub(crate) fn bit((region_index, tile_index): Play) -> u128 {
+        1 << (9 * usize::from(region_index) + usize::from(tile_index))
+    }
+
+    /// Returns the plays set in the given mask, where each play is bit `9 * region + tile`.
+    pub(crate) fn from_mask(plays: u128) -> Self {
+        Self { plays }
+    }
+
+    /// Check if the given play is in the remaining plays.
+    pub fn contains(&self, play: Play) -> bool {
+        self.plays & Self::bit(play) != 0
+    }
+
+    /// Returns a random play among the remaining plays, choosing a region uniformly first and
+    /// then a tile within it, or `None` if there are no plays left.
+    pub(crate) fn choose_by_region(&self, rng: &mut impl Rng) -> Option<Play> {
+        let region_tiles = |region: usize| (self.plays >> (9 * region)) as u16 & 0x1FF;
+        let regions = (0..BoardIndex::N)
+            .filter(|&region| region_tiles(region) != 0)
+            .fold(0, |mask, region| mask | 1 << region);
+        if regions == 0 {
+            return None;
+        }
+        let region = nth_bit(regions, rng);
+        let tile = nth_bit(region_tiles(region), rng);
+        let index =
+            |i| BoardIndex::try_from(i).expect("Invariant: Only the lowest 81 bits are ever set.");
+        Some((index(region), index(tile)))
+    }
+}
+
+/// Returns the position of a uniformly chosen set bit of `mask`.
+///
+/// The caller must ensure `mask` is not zero.
+fn nth_bit(mut mask: u16, rng: &mut impl Rng) -> usize {
+    for _ in 0..rng.gen_range(0..mask.count_ones()) {
+        mask &= mask - 1;
+    }
+    mask.trailing_zeros() as usize
+}
+
+impl Iterator for LegalMoves {
+    type Item = Play;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.plays == 0 {
+            return None;
+        }
+        let bit = self.plays.trailing_zeros() as usize;
+        self.plays &= self.plays - 1;
+        let index =
+            |i| BoardIndex::try_from(i).expect("Invariant: Only the lowest 81 bits are ever set.");
+        Some((index(bit / 9), index(bit % 9)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.plays.count_ones() as usize;
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        for _ in 0..n {
+            if self.plays == 0 {
+                break;
+            }
+            self.plays &= self.plays - 1;
+        }
+        self.next()
+    }
+}
+
+impl ExactSizeIterator for LegalMoves {}
+
+impl FusedIterator for LegalMoves {}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{all_plays, random_positions, rule_sets};
+
+    #[test]
+    fn enablement_matches_legality() {
+        for rules in rule_sets() {
+            for seed in 0..10 {
+                for game in random_positions(rules, seed) {
+                    for (region_index, tile_index) in all_plays() {
+                        let is_enabled = game.is_region_enabled(region_index)
+                            && game.board[region_index].is_tile_enabled(tile_index, &rules);
+                        assert_eq!(
+                            is_enabled,
+                            game.is_legal((region_index, tile_index)),
+                            "{rules:?} {region_index:?} {tile_index:?}\n{game}"
+                        );
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,393 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BoardIndex, Game, InvalidGame, MarkTileResult, Play};
+
+/// A [`Game`] together with a record of every play made in it, which allows undoing and redoing
+/// plays.
+///
+/// Undoing a play reverts it with [`Game::unmark_tile`] and keeps it in the record, so it can be
+/// redone until a different play is made.
+///
+/// Deserialized histories are checked to be consistent, so that every recorded play can be undone
+/// and redone. See [`InvalidHistory`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "GameHistoryFields")]
+pub struct GameHistory {
+    /// The game after applying the first `ply` plays.
+    game: Game,
+    /// All the recorded plays, including the ones that have been undone.
+    plays: Vec<Play>,
+    /// The number of plays that are currently applied to `game`.
+    ply: usize,
+    /// The [`Game::previous_play_index`] of the starting game.
+    initial_previous_play_index: Option<BoardIndex>,
+}
+
+impl GameHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the game at the current ply.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Returns all the recorded plays, including the ones that have been undone.
+    pub fn plays(&self) -> &[Play] {
+        &self.plays
+    }
+
+    /// Returns the number of plays that are currently applied.
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    /// Returns the game the history started from, before any recorded play.
+    pub fn initial_game(&self) -> Game {
+        let mut game = self.game.clone();
+        for ply in (0..self.ply).rev() {
+            let previous_play_index = match ply {
+                0 => self.initial_previous_play_index,
+                ply => Some(self.plays[ply - 1].1),
+            };
+            assert!(
+                game.unmark_tile(self.plays[ply], previous_play_index),
+                "recorded plays should always be reverted in the reverse order they were made in."
+            );
+        }
+        game
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.ply > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.ply < self.plays.len()
+    }
+
+    /// Make the given play in the current game and record it.
+    ///
+    /// If the play is valid, this discards all the plays that have been undone.
+    pub fn mark_tile(&mut self, play: Play) -> MarkTileResult {
+        let result = self.game.mark_tile(play);
+        if !matches!(result, MarkTileResult::NoChange) {
+            self.plays.truncate(self.ply);
+            self.plays.push(play);
+            self.ply += 1;
+        }
+        result
+    }
+
+    /// Revert the last applied play and return it, or `None` if there's nothing to undo.
+    pub fn undo(&mut self) -> Option<Play> {
+        let play = *self.plays.get(self.ply.checked_sub(1)?)?;
+        let previous_play_index = match self.ply {
+            1 => self.initial_previous_play_index,
+            ply => Some(self.plays[ply - 2].1),
+        };
+        assert!(
+            self.game.unmark_tile(play, previous_play_index),
+            "recorded plays should always be reverted in the reverse order they were made in."
+        );
+        self.ply -= 1;
+        Some(play)
+    }
+
+    /// Reapply the last undone play and return it, or `None` if there's nothing to redo.
+    pub fn redo(&mut self) -> Option<Play> {
+        let play = *self.plays.get(self.ply)?;
+        assert!(
+            !matches!(self.game.mark_tile(play), MarkTileResult::NoChange),
+            "recorded plays should always be valid when reapplied in order."
+        );
+        self.ply += 1;
+        Some(play)
+    }
+
+    /// Undo or redo plays until the given number of plays are applied.
+    ///
+    /// The ply is clamped to the number of recorded plays.
+    pub fn seek(&mut self, ply: usize) {
+        let ply = ply.min(self.plays.len());
+        while self.ply > ply {
+            self.undo();
+        }
+        while self.ply < ply {
+            self.redo();
+        }
+    }
+}
+
+impl From<Game> for GameHistory {
+    /// Start recording plays from the given game.
+    fn from(game: Game) -> Self {
+        Self {
+            initial_previous_play_index: game.previous_play_index,
+            game,
+            plays: Vec::new(),
+            ply: 0,
+        }
+    }
+}
+
+/// The serialized fields of a [`GameHistory`], which are validated when deserializing.
+#[derive(Deserialize)]
+struct GameHistoryFields {
+    game: Game,
+    plays: Vec<Play>,
+    ply: usize,
+    initial_previous_play_index: Option<BoardIndex>,
+}
+
+impl TryFrom<GameHistoryFields> for GameHistory {
+    type Error = InvalidHistory;
+
+    /// Undoes the applied plays from the game to find the starting game, then checks that making
+    /// all the recorded plays from it is valid and gives back the game after the applied ones.
+    fn try_from(fields: GameHistoryFields) -> Result<Self, Self::Error> {
+        let GameHistoryFields {
+            game,
+            plays,
+            ply,
+            initial_previous_play_index,
+        } = fields;
+        if ply > plays.len() {
+            return Err(InvalidHistory::InvalidPly {
+                ply,
+                n_plays: plays.len(),
+            });
+        }
+
+        let mut start = game.clone();
+        for i in (0..ply).rev() {
+            let previous_play_index = match i {
+                0 => initial_previous_play_index,
+                i => Some(plays[i - 1].1),
+            };
+            if !start.unmark_tile(plays[i], previous_play_index) {
+                return Err(InvalidHistory::InvalidPlay(i));
+            }
+        }
+        if start.previous_play_index != initial_previous_play_index {
+            return Err(InvalidHistory::InvalidInitialPreviousPlayIndex);
+        }
+        start.validate().map_err(InvalidHistory::InvalidStart)?;
+
+        let mut replayed = start;
+        for (i, &play) in plays.iter().enumerate() {
+            if i == ply && replayed != game {
+                return Err(InvalidHistory::InvalidPlay(i.saturating_sub(1)));
+            }
+            if replayed.try_mark_tile(play).is_err() {
+                return Err(InvalidHistory::InvalidPlay(i));
+            }
+        }
+        if ply == plays.len() && replayed != game {
+            return Err(InvalidHistory::InvalidPlay(ply.saturating_sub(1)));
+        }
+
+        Ok(Self {
+            game,
+            plays,
+            ply,
+            initial_previous_play_index,
+        })
+    }
+}
+
+/// The reason a deserialized [`GameHistory`] is inconsistent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidHistory {
+    /// More plays are applied than recorded.
+    InvalidPly { ply: usize, n_plays: usize },
+    /// The recorded play with the given index can't be undone or redone where it is.
+    InvalidPlay(usize),
+    /// No play is applied, and the previous play index of the game doesn't match the recorded one
+    /// of the starting game.
+    InvalidInitialPreviousPlayIndex,
+    /// The game found by undoing the applied plays is inconsistent.
+    InvalidStart(InvalidGame),
+}
+
+impl Display for InvalidHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPly { ply, n_plays } => {
+                write!(f, "{ply} plays are applied but only {n_plays} are recorded")
+            }
+            Self::InvalidPlay(i) => write!(f, "recorded play {i} can't be undone or redone"),
+            Self::InvalidInitialPreviousPlayIndex => write!(
+                f,
+                "the previous play index of the starting game doesn't match the recorded one"
+            ),
+            Self::InvalidStart(error) => write!(f, "the starting game is inconsistent: {error}"),
+        }
+    }
+}
+
+impl Error for InvalidHistory {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{random_plays, random_positions, rule_sets};
+
+    /// Returns a history of the game played with [`random_plays`] with the given seed.
+    fn played_history(rules: crate::RuleSet, seed: u64) -> GameHistory {
+        let mut history = GameHistory::from(Game::with_rules(rules));
+        for play in random_plays(rules, seed) {
+            assert!(!matches!(history.mark_tile(play), MarkTileResult::NoChange));
+        }
+        history
+    }
+
+    #[test]
+    fn undo_redo_and_seek_restore_positions() {
+        for rules in rule_sets() {
+            for seed in 0..5 {
+                let positions = random_positions(rules, seed);
+                let mut history = played_history(rules, seed);
+                assert_eq!(history.ply(), positions.len() - 1);
+                assert_eq!(history.redo(), None);
+
+                for ply in (0..positions.len() - 1).rev() {
+                    assert_eq!(history.undo(), Some(history.plays()[ply]));
+                    assert_eq!(history.game(), &positions[ply], "{rules:?} ply {ply}");
+                    assert_eq!(
+                        history.game().allowed_region_index(),
+                        positions[ply].allowed_region_index()
+                    );
+                }
+                assert_eq!(history.undo(), None);
+                for (ply, position) in positions.iter().enumerate().skip(1) {
+                    assert_eq!(history.redo(), Some(history.plays()[ply - 1]));
+                    assert_eq!(history.game(), position, "{rules:?} ply {ply}");
+                }
+
+                for ply in [positions.len() / 2, 3, positions.len() + 10, 0, 7] {
+                    history.seek(ply);
+                    let ply = ply.min(positions.len() - 1);
+                    assert_eq!(history.ply(), ply);
+                    assert_eq!(history.game(), &positions[ply]);
+                    assert_eq!(
+                        history.game().allowed_region_index(),
+                        positions[ply].allowed_region_index()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn play_after_undo_discards_undone_plays() {
+        let mut history = played_history(Default::default(), 0);
+        history.seek(4);
+        let undone = history.plays()[4];
+        let play = history
+            .game()
+            .legal_moves()
+            .find(|&play| play != undone)
+            .unwrap();
+        assert!(!matches!(history.mark_tile(play), MarkTileResult::NoChange));
+        assert_eq!(history.ply(), 5);
+        assert_eq!(history.plays().len(), 5);
+        assert_eq!(history.plays()[4], play);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn start_from_mid_game() {
+        let rules = crate::RuleSet {
+            won_regions_playable: true,
+            ..Default::default()
+        };
+        let plays = random_plays(rules, 1);
+        let positions = random_positions(rules, 1);
+        let mut history = GameHistory::from(positions[20].clone());
+        for &play in &plays[20..] {
+            assert!(!matches!(history.mark_tile(play), MarkTileResult::NoChange));
+        }
+        assert_eq!(history.game(), positions.last().unwrap());
+        history.seek(0);
+        assert_eq!(history.game(), &positions[20]);
+        assert!(!history.can_undo());
+        history.seek(5);
+        assert_eq!(history.game(), &positions[25]);
+    }
+
+    /// Returns the fields of the given history.
+    fn fields(history: &GameHistory) -> GameHistoryFields {
+        GameHistoryFields {
+            game: history.game.clone(),
+            plays: history.plays.clone(),
+            ply: history.ply,
+            initial_previous_play_index: history.initial_previous_play_index,
+        }
+    }
+
+    #[test]
+    fn valid_fields() {
+        let mut history = GameHistory::from(random_positions(Default::default(), 2)[10].clone());
+        for play in random_plays(Default::default(), 2).into_iter().skip(10) {
+            history.mark_tile(play);
+        }
+        for ply in [0, 3, history.plays().len()] {
+            history.seek(ply);
+            assert_eq!(GameHistory::try_from(fields(&history)), Ok(history.clone()));
+        }
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let mut history = played_history(Default::default(), 3);
+        history.seek(6);
+
+        let mut invalid = fields(&history);
+        invalid.ply = history.plays().len() + 1;
+        assert_eq!(
+            GameHistory::try_from(invalid),
+            Err(InvalidHistory::InvalidPly {
+                ply: history.plays().len() + 1,
+                n_plays: history.plays().len(),
+            })
+        );
+
+        let mut invalid = fields(&history);
+        invalid.ply = 5;
+        assert_eq!(
+            GameHistory::try_from(invalid),
+            Err(InvalidHistory::InvalidPlay(4))
+        );
+
+        let mut invalid = fields(&history);
+        invalid.plays.swap(8, 9);
+        assert!(matches!(
+            GameHistory::try_from(invalid),
+            Err(InvalidHistory::InvalidPlay(_))
+        ));
+
+        let mut invalid = fields(&history);
+        invalid.initial_previous_play_index = Some(BoardIndex::Center);
+        assert_eq!(
+            GameHistory::try_from(invalid),
+            Err(InvalidHistory::InvalidStart(
+                InvalidGame::InvalidPreviousPlayIndex(Some(BoardIndex::Center))
+            ))
+        );
+
+        history.seek(0);
+        let mut invalid = fields(&history);
+        invalid.initial_previous_play_index = Some(BoardIndex::Center);
+        assert_eq!(
+            GameHistory::try_from(invalid),
+            Err(InvalidHistory::InvalidInitialPreviousPlayIndex)
+        );
+    }
+}
@@ -1,16 +1,46 @@
 pub mod ai;
+pub mod analysis;
 pub mod board;
+pub mod clock;
+pub mod diagram;
+pub mod encoding;
+pub mod fast_game;
 pub mod game;
+pub mod history;
 pub mod is_none_or;
+pub mod nested;
+pub mod notation;
+pub mod perft;
 pub mod player;
+pub mod position;
 pub mod region;
+pub mod replay;
+pub mod rules;
+pub mod session;
+pub mod symmetry;
+#[cfg(test)]
+mod testing;
 pub mod tile;
+pub mod validate;
+mod zobrist;
 
 pub use {
+    analysis::{Analysis, Threats},
     board::{Board, BoardEnumerate, BoardIndex, BoardItem, BoardOutcome, BoardState},
-    game::{Game, Play},
+    clock::{Clock, ClockState, FlagFall, TimeControl, TimeSource, Untimed},
+    encoding::PositionId,
+    fast_game::FastGame,
+    game::{Game, IllegalMove, LegalMoves, MoveOutcome, Play},
+    history::{GameHistory, InvalidHistory},
     is_none_or::IsNoneOr,
+    nested::{InvalidNestedGame, NestedGame},
+    notation::{GameRecord, PlayNotation, RecordError},
     player::Player,
     region::Region,
+    replay::{Positions, ReplayError},
+    rules::{GameMode, ParseRulesError, RuleSet, UnsupportedRules},
+    session::{GameResult, GameSession, InvalidSession, SessionError, SessionState, Termination},
+    symmetry::Symmetry,
     tile::{MarkTileResult, Tile},
+    validate::{InvalidGame, InvalidRegionState},
 };
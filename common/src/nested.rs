@@ -0,0 +1,536 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Geometry, Grid},
+    rules::UnsupportedRules,
+    BoardIndex, BoardItem, BoardState, Game, MarkTileResult, Player, RuleSet, Tile,
+};
+
+/// A super tic-tac-toe game with any number of nested levels of boards.
+///
+/// Every board at every level has the same [`Grid`] geometry, which is the standard 3x3 grid
+/// unless the game is created with [`NestedGame::with_grid`]. With the standard grid, a game of
+/// depth 1 is a plain tic-tac-toe board, depth 2 is the standard game played by [`Game`], depth 3
+/// has 729 tiles in 81 regions grouped into 9 super regions, and so on.
+///
+/// Plays are tile indices. Written in base `n`, where `n` is the number of items on a board, the
+/// digits of a tile index from the most significant one are the item indices of the boards
+/// containing the tile from the outermost one down. With the standard grid, the digits of a tile
+/// in a 2-level game are its region index and its tile index as `usize`. See
+/// [`NestedGame::path`] and [`NestedGame::tile_at`].
+///
+/// After a play, the opponent is sent down the tree: they have to play in the board at the path
+/// made of all the digits of the play except the first one. If that board is closed, they're sent
+/// to its parent board instead, and so on up to the whole game. In a 2-level game, this is the
+/// same as being sent to the region with the tile index of the play, or anywhere if it's closed.
+///
+/// Nested games are always played with the default [`RuleSet`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "NestedGameFields")]
+pub struct NestedGame {
+    grid: Grid,
+    depth: usize,
+    tiles: Vec<Tile>,
+    /// The states of the boards at each level.
+    ///
+    /// `boards[0]` only has the state of the whole game, and `boards[level]` has `n^level`
+    /// boards. Board `i` at `level` consists of boards `n * i` to `n * i + n - 1` at
+    /// `level + 1`, or tiles `n * i` to `n * i + n - 1` at the deepest level `depth - 1`.
+    boards: Vec<Vec<BoardState>>,
+    current_player: Player,
+    previous_play: Option<usize>,
+}
+
+impl NestedGame {
+    /// Returns a new game with the given number of levels of standard 3x3 boards.
+    ///
+    /// Panics if `depth` is 0.
+    pub fn new(depth: usize) -> Self {
+        Self::with_grid(depth, Grid::default())
+    }
+
+    /// Returns a new game with the given number of levels of boards with the given geometry.
+    ///
+    /// Panics if `depth` is 0 or if the game would have more tiles than fit in a `usize`.
+    pub fn with_grid(depth: usize, grid: Grid) -> Self {
+        assert!(depth > 0, "a game should have at least one level.");
+
+        let n_tiles = u32::try_from(depth)
+            .ok()
+            .and_then(|depth| grid.n_items().checked_pow(depth))
+            .expect("the number of tiles should fit in a usize.");
+        let mut game = Self {
+            grid,
+            depth,
+            tiles: vec![Tile::Unmarked; n_tiles],
+            boards: Vec::new(),
+            current_player: Player::default(),
+            previous_play: None,
+        };
+        game.boards = (0..depth)
+            .map(|level| vec![BoardState::InProgress; game.pow(level)])
+            .collect();
+        game
+    }
+
+    /// Returns the geometry of every board in the game.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns all the tiles of the game, indexed by play.
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// Returns the state of board `index` at the given level, where level 0 is the whole game.
+    pub fn board_state(&self, level: usize, index: usize) -> BoardState {
+        self.boards[level][index]
+    }
+
+    pub fn state(&self) -> BoardState {
+        self.boards[0][0]
+    }
+
+    pub fn current_player(&self) -> Player {
+        self.current_player
+    }
+
+    pub fn previous_play(&self) -> Option<usize> {
+        self.previous_play
+    }
+
+    /// Returns the path of item indices from the outermost board down to the given tile.
+    pub fn path(&self, tile: usize) -> Vec<usize> {
+        (0..self.depth)
+            .rev()
+            .map(|level| tile / self.pow(level) % self.grid.n_items())
+            .collect()
+    }
+
+    /// Returns the tile at the given path of item indices, or `None` if the path doesn't have
+    /// exactly as many indices as the game has levels or an index is out of bounds.
+    pub fn tile_at(&self, path: &[usize]) -> Option<usize> {
+        let n = self.grid.n_items();
+        (path.len() == self.depth && path.iter().all(|&index| index < n))
+            .then(|| path.iter().fold(0, |tile, &index| tile * n + index))
+    }
+
+    /// Get the level and the index of the board that the current player has to play in.
+    /// If it's `(0, 0)`, that means the player can play anywhere.
+    pub fn allowed_board(&self) -> (usize, usize) {
+        if let Some(previous_play) = self.previous_play {
+            // A board is only open if all the boards containing it are in progress too, so walk
+            // down the path and stop at the first closed board.
+            let path = previous_play % self.pow(self.depth - 1);
+            let open_boards = (1..self.depth)
+                .map(|level| (level, path / self.pow(self.depth - 1 - level)))
+                .take_while(|&(level, index)| self.boards[level][index].is_markable());
+            if let Some(board) = open_boards.last() {
+                return board;
+            }
+        }
+        (0, 0)
+    }
+
+    /// Returns an iterator over all the valid plays for the current player, in tile order.
+    pub fn legal_moves(&self) -> impl Iterator<Item = usize> + '_ {
+        let in_progress = self.state().is_markable();
+        let (level, index) = self.allowed_board();
+        let span = self.pow(self.depth - level);
+        (index * span..(index + 1) * span)
+            .filter(move |&tile| in_progress && self.is_tile_open(tile))
+    }
+
+    /// Check if the given play is valid for the current player.
+    pub fn is_legal(&self, tile: usize) -> bool {
+        let (level, index) = self.allowed_board();
+        self.state().is_markable()
+            && tile < self.tiles.len()
+            && tile / self.pow(self.depth - level) == index
+            && self.is_tile_open(tile)
+    }
+
+    /// Mark the given tile for the current player, then update the states of all the boards
+    /// containing it accordingly.
+    pub fn mark_tile(&mut self, tile: usize) -> MarkTileResult {
+        if !self.is_legal(tile) {
+            return MarkTileResult::NoChange;
+        }
+
+        self.tiles[tile] = Tile::Marked(self.current_player);
+        self.current_player = self.current_player.other();
+        self.previous_play = Some(tile);
+
+        for level in (0..self.depth).rev() {
+            let index = tile / self.pow(self.depth - level);
+            let state = self.get_board_state(level, index);
+            self.boards[level][index] = state;
+            match state {
+                BoardState::InProgress => break,
+                BoardState::Complete(outcome) if level == 0 => {
+                    return MarkTileResult::OutcomeDecided(outcome)
+                }
+                BoardState::Complete(_) => (),
+            }
+        }
+
+        MarkTileResult::TileMarked
+    }
+
+    /// Check if the given tile is unmarked and all the boards containing it are in progress.
+    fn is_tile_open(&self, tile: usize) -> bool {
+        self.tiles[tile].is_markable()
+            && (1..self.depth)
+                .all(|level| self.boards[level][tile / self.pow(self.depth - level)].is_markable())
+    }
+
+    /// Computes the state of board `index` at the given level from its items.
+    fn get_board_state(&self, level: usize, index: usize) -> BoardState {
+        let n = self.grid.n_items();
+        let items = n * index..n * (index + 1);
+        if level == self.depth - 1 {
+            self.grid.get_state(&self.tiles[items])
+        } else {
+            self.grid.get_state(&self.boards[level + 1][items])
+        }
+    }
+
+    /// Check that this game is consistent, which is always the case for games made only through
+    /// [`NestedGame::mark_tile`].
+    ///
+    /// A game is consistent when its tiles and boards have the sizes its grid and depth call for,
+    /// the state of every board matches its items, Circle has made as many plays as Cross or one
+    /// more, and the previous play, if any, is a tile marked by the player who made it.
+    ///
+    /// Returns the first invariant that doesn't hold otherwise.
+    pub fn validate(&self) -> Result<(), InvalidNestedGame> {
+        let n_tiles = u32::try_from(self.depth)
+            .ok()
+            .and_then(|depth| self.grid.n_items().checked_pow(depth));
+        if self.depth == 0
+            || n_tiles != Some(self.tiles.len())
+            || self.boards.len() != self.depth
+            || (0..self.depth).any(|level| self.boards[level].len() != self.pow(level))
+        {
+            return Err(InvalidNestedGame::InvalidSize);
+        }
+
+        for level in (0..self.depth).rev() {
+            for index in 0..self.pow(level) {
+                if self.boards[level][index] != self.get_board_state(level, index) {
+                    return Err(InvalidNestedGame::InvalidBoardState { level, index });
+                }
+            }
+        }
+
+        let count = |player| {
+            self.tiles
+                .iter()
+                .filter(|&&tile| tile == Tile::Marked(player))
+                .count()
+        };
+        let (n_circle, n_cross) = (count(Player::Circle), count(Player::Cross));
+        let expected_player = match n_circle.checked_sub(n_cross) {
+            Some(0) => Player::Circle,
+            Some(1) => Player::Cross,
+            _ => return Err(InvalidNestedGame::InvalidMarkCounts { n_circle, n_cross }),
+        };
+        if self.current_player != expected_player {
+            return Err(InvalidNestedGame::InvalidMarkCounts { n_circle, n_cross });
+        }
+
+        let is_previous_play_valid = match self.previous_play {
+            None => n_circle + n_cross == 0,
+            Some(tile) => self.tiles.get(tile) == Some(&Tile::Marked(self.current_player.other())),
+        };
+        if !is_previous_play_valid {
+            return Err(InvalidNestedGame::InvalidPreviousPlay(self.previous_play));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of items on a board raised to the given power, which is the number of
+    /// boards at that level or the number of tiles in a board that many levels above the tiles.
+    fn pow(&self, exponent: usize) -> usize {
+        self.grid.n_items().pow(exponent as u32)
+    }
+}
+
+impl Default for NestedGame {
+    /// Returns a new 2-level game, which is the standard game.
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl TryFrom<&Game> for NestedGame {
+    type Error = UnsupportedRules;
+
+    /// Converts a standard game into a 2-level game, which fails if the game isn't played with
+    /// the default rules.
+    fn try_from(game: &Game) -> Result<Self, Self::Error> {
+        if game.rules != RuleSet::default() {
+            return Err(UnsupportedRules(game.rules));
+        }
+
+        let mut nested_game = Self::new(2);
+        for (region_index, region) in game.board.enumerate() {
+            let offset = BoardIndex::N * usize::from(region_index);
+            nested_game.boards[1][usize::from(region_index)] = region.state;
+            for (tile_index, &tile) in region.board.enumerate() {
+                nested_game.tiles[offset + usize::from(tile_index)] = tile;
+            }
+        }
+        nested_game.boards[0][0] = game.state;
+        nested_game.current_player = game.current_player;
+        nested_game.previous_play = game.previous_play_index.and_then(|tile_index| {
+            // Any tile with the previous play index marked by the last player leads to the same
+            // allowed board.
+            let last_player = Tile::Marked(game.current_player.other());
+            let (region_index, _) = game
+                .board
+                .enumerate()
+                .find(|(_, region)| region.board[tile_index] == last_player)?;
+            nested_game.tile_at(&[usize::from(region_index), usize::from(tile_index)])
+        });
+        Ok(nested_game)
+    }
+}
+
+/// The serialized fields of a [`NestedGame`], which are validated when deserializing.
+#[derive(Deserialize)]
+struct NestedGameFields {
+    grid: Grid,
+    depth: usize,
+    tiles: Vec<Tile>,
+    boards: Vec<Vec<BoardState>>,
+    current_player: Player,
+    previous_play: Option<usize>,
+}
+
+impl TryFrom<NestedGameFields> for NestedGame {
+    type Error = InvalidNestedGame;
+
+    fn try_from(fields: NestedGameFields) -> Result<Self, Self::Error> {
+        let game = Self {
+            grid: fields.grid,
+            depth: fields.depth,
+            tiles: fields.tiles,
+            boards: fields.boards,
+            current_player: fields.current_player,
+            previous_play: fields.previous_play,
+        };
+        game.validate()?;
+        Ok(game)
+    }
+}
+
+/// The reason a [`NestedGame`] is inconsistent, as returned by [`NestedGame::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidNestedGame {
+    /// The number of tiles or boards doesn't match the grid and the depth of the game.
+    InvalidSize,
+    /// The state of the given board doesn't match its items.
+    InvalidBoardState { level: usize, index: usize },
+    /// The number of marks of each player doesn't match the player to move.
+    InvalidMarkCounts { n_circle: usize, n_cross: usize },
+    /// The given previous play isn't a tile marked by the last player.
+    InvalidPreviousPlay(Option<usize>),
+}
+
+impl Display for InvalidNestedGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize => write!(
+                f,
+                "the number of tiles or boards doesn't match the grid and the depth"
+            ),
+            Self::InvalidBoardState { level, index } => write!(
+                f,
+                "the state of board {index} at level {level} doesn't match its items"
+            ),
+            Self::InvalidMarkCounts { n_circle, n_cross } => write!(
+                f,
+                "{n_circle} circles and {n_cross} crosses don't match the player to move"
+            ),
+            Self::InvalidPreviousPlay(Some(tile)) => {
+                write!(f, "tile {tile} hasn't been marked by the last player")
+            }
+            Self::InvalidPreviousPlay(None) => {
+                write!(f, "a play has been made but there's no previous play")
+            }
+        }
+    }
+}
+
+impl Error for InvalidNestedGame {}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+
+    use super::*;
+    use crate::testing::{random_positions, rule_sets};
+
+    /// Returns the fields of the given game, to be deserialized after being tampered with.
+    fn fields(game: &NestedGame) -> NestedGameFields {
+        let game = game.clone();
+        NestedGameFields {
+            grid: game.grid,
+            depth: game.depth,
+            tiles: game.tiles,
+            boards: game.boards,
+            current_player: game.current_player,
+            previous_play: game.previous_play,
+        }
+    }
+
+    #[test]
+    fn played_games_are_valid() {
+        for depth in 1..=3 {
+            let mut rng = StdRng::seed_from_u64(depth as u64);
+            let mut game = NestedGame::new(depth);
+            assert_eq!(NestedGame::try_from(fields(&game)), Ok(game.clone()));
+            while let Some(tile) = game.legal_moves().choose(&mut rng) {
+                game.mark_tile(tile);
+                assert_eq!(NestedGame::try_from(fields(&game)), Ok(game.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn conversion_from_game() {
+        for game in random_positions(RuleSet::default(), 0) {
+            let nested_game = NestedGame::try_from(&game).unwrap();
+            assert_eq!(nested_game.validate(), Ok(()));
+            assert_eq!(nested_game.state(), game.state);
+            let plays: Vec<usize> = game
+                .legal_moves()
+                .map(|(region_index, tile_index)| {
+                    BoardIndex::N * usize::from(region_index) + usize::from(tile_index)
+                })
+                .collect();
+            assert_eq!(nested_game.legal_moves().collect::<Vec<_>>(), plays);
+        }
+        for rules in rule_sets().into_iter().skip(1) {
+            assert_eq!(
+                NestedGame::try_from(&Game::with_rules(rules)),
+                Err(UnsupportedRules(rules))
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_size() {
+        let game = NestedGame::new(2);
+        for depth in [0, 1, 3, usize::MAX] {
+            let fields = NestedGameFields {
+                depth,
+                ..fields(&game)
+            };
+            assert_eq!(
+                NestedGame::try_from(fields),
+                Err(InvalidNestedGame::InvalidSize),
+                "depth {depth}"
+            );
+        }
+        let mut tampered = fields(&game);
+        tampered.tiles.pop();
+        assert_eq!(
+            NestedGame::try_from(tampered),
+            Err(InvalidNestedGame::InvalidSize)
+        );
+        let mut tampered = fields(&game);
+        tampered.boards[1].pop();
+        assert_eq!(
+            NestedGame::try_from(tampered),
+            Err(InvalidNestedGame::InvalidSize)
+        );
+        let tampered = NestedGameFields {
+            grid: Grid::new(4, 3),
+            ..fields(&game)
+        };
+        assert_eq!(
+            NestedGame::try_from(tampered),
+            Err(InvalidNestedGame::InvalidSize)
+        );
+    }
+
+    #[test]
+    fn invalid_board_state() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut game = NestedGame::new(2);
+        let index = loop {
+            game.mark_tile(game.legal_moves().choose(&mut rng).unwrap());
+            if let Some(index) = (0..9).find(|&index| !game.board_state(1, index).is_markable()) {
+                break index;
+            }
+        };
+
+        let mut tampered = fields(&game);
+        tampered.boards[1][index] = BoardState::InProgress;
+        assert_eq!(
+            NestedGame::try_from(tampered),
+            Err(InvalidNestedGame::InvalidBoardState { level: 1, index })
+        );
+        let mut tampered = fields(&game);
+        tampered.boards[0][0] = BoardState::Complete(crate::BoardOutcome::Draw);
+        assert_eq!(
+            NestedGame::try_from(tampered),
+            Err(InvalidNestedGame::InvalidBoardState { level: 0, index: 0 })
+        );
+    }
+
+    #[test]
+    fn invalid_mark_counts() {
+        let mut game = NestedGame::new(2);
+        game.mark_tile(40);
+        let tampered = NestedGameFields {
+            current_player: Player::Circle,
+            ..fields(&game)
+        };
+        assert_eq!(
+            NestedGame::try_from(tampered),
+            Err(InvalidNestedGame::InvalidMarkCounts {
+                n_circle: 1,
+                n_cross: 0
+            })
+        );
+        let mut tampered = fields(&game);
+        tampered.tiles[40] = Tile::Marked(Player::Cross);
+        assert_eq!(
+            NestedGame::try_from(tampered),
+            Err(InvalidNestedGame::InvalidMarkCounts {
+                n_circle: 0,
+                n_cross: 1
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_previous_play() {
+        let mut game = NestedGame::new(2);
+        game.mark_tile(40);
+        for previous_play in [None, Some(0), Some(81), Some(usize::MAX)] {
+            let tampered = NestedGameFields {
+                previous_play,
+                ..fields(&game)
+            };
+            assert_eq!(
+                NestedGame::try_from(tampered),
+                Err(InvalidNestedGame::InvalidPreviousPlay(previous_play))
+            );
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Write},
+    str::FromStr,
+};
+
+use crate::{
+    position::ParsePositionError, BoardIndex, Game, GameHistory, Play, ReplayError, RuleSet,
+};
+
+/// The textual notation of a [`Play`].
+///
+/// A play is written as the coordinates of its tile on the whole 9x9 grid: a column letter from
+/// `a` to `i` counting from the left, followed by a row number from `1` to `9` counting from the
+/// top. For example, `a1` is the upper left tile of the upper left region, and `e5` is the center
+/// tile of the center region.
+///
+/// A single [`BoardIndex`] is written the same way on a 3x3 grid, from `a1` to `c3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayNotation(pub Play);
+
+impl Display for PlayNotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (row, column) = grid_coordinates(self.0);
+        write_coordinates(f, row, column)
+    }
+}
+
+impl FromStr for PlayNotation {
+    type Err = ParseNotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (row, column) = parse_coordinates(s, 9)?;
+        play_at(row, column)
+            .map(Self)
+            .ok_or_else(|| ParseNotationError::InvalidCoordinates(s.to_owned()))
+    }
+}
+
+/// Returns the zero-based `(row, column)` of the tile of the given play on the whole 9x9 grid.
+pub(crate) fn grid_coordinates((region_index, tile_index): Play) -> (usize, usize) {
+    (
+        region_index.row() * 3 + tile_index.row(),
+        region_index.column() * 3 + tile_index.column(),
+    )
+}
+
+/// Returns the play of the tile at the given zero-based row and column of the whole 9x9 grid, or
+/// `None` if either is out of bounds.
+pub(crate) fn play_at(row: usize, column: usize) -> Option<Play> {
+    Some((
+        BoardIndex::from_row_column(row / 3, column / 3)?,
+        BoardIndex::from_row_column(row % 3, column % 3)?,
+    ))
+}
+
+impl Display for BoardIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_coordinates(f, self.row(), self.column())
+    }
+}
+
+impl FromStr for BoardIndex {
+    type Err = ParseNotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (row, column) = parse_coordinates(s, 3)?;
+        Self::from_row_column(row, column)
+            .ok_or_else(|| ParseNotationError::InvalidCoordinates(s.to_owned()))
+    }
+}
+
+fn write_coordinates(f: &mut fmt::Formatter<'_>, row: usize, column: usize) -> fmt::Result {
+    f.write_char(char::from(b'a' + column as u8))?;
+    write!(f, "{}", row + 1)
+}
+
+/// Parses coordinates on a `size` by `size` grid into a zero-based `(row, column)` pair.
+fn parse_coordinates(s: &str, size: usize) -> Result<(usize, usize), ParseNotationError> {
+    let error = || ParseNotationError::InvalidCoordinates(s.to_owned());
+    let mut chars = s.chars();
+    let (Some(column), Some(row), None) = (chars.next(), chars.next(), chars.next()) else {
+        return Err(error());
+    };
+    let column = (column as usize).wrapping_sub('a' as usize);
+    let row = (row as usize).wrapping_sub('1' as usize);
+    if row < size && column < size {
+        Ok((row, column))
+    } else {
+        Err(error())
+    }
+}
+
+/// A record of a whole game: a list of plays with optional header tags.
+///
+/// The textual form is similar to PGN. Each tag is written on its own line as `[Key "Value"]`,
+/// followed by an empty line and the numbered plays, e.g.
+///
+/// ```text
+/// [Circle "Alice"]
+/// [Cross "Bob"]
+/// [Date "2023.10.21"]
+/// [Result "Circle"]
+///
+/// 1. e5 e4 2. e2 e6 3. e8 d5
+/// ```
+///
+/// Conventional tags are `Circle` and `Cross` for the players, `Date`, `Result`, `Rules` and
+/// `Position`, but any tag is preserved. When parsing, move numbers are optional.
+///
+/// The `Rules` and `Position` tags describe the game the plays start from, see
+/// [`GameRecord::initial_game`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameRecord {
+    /// The header tags as key-value pairs, in the order they're written in.
+    pub tags: Vec<(String, String)>,
+    /// The plays made in the game, starting from the game given by the tags.
+    pub plays: Vec<Play>,
+}
+
+impl GameRecord {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of the first tag with the given key.
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of the tag with the given key, adding the tag if there isn't one yet.
+    pub fn set_tag(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        let value = value.into();
+        match self.tags.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.tags.push((key, value)),
+        }
+    }
+
+    /// Returns the game the recorded plays start from.
+    ///
+    /// This is the game of the `Position` tag if there is one, as a position string, and an
+    /// empty board otherwise. The game is played with the rules of the `Rules` tag, written like
+    /// the [`Display`] implementation of [`RuleSet`], or the default rules without it. A
+    /// `Position` tag with rules other than the ones of the `Rules` tag is rejected.
+    pub fn initial_game(&self) -> Result<Game, RecordError> {
+        let rules = match self.tag("Rules") {
+            Some(rules) => rules
+                .parse()
+                .map_err(|_| RecordError::InvalidRules(rules.to_owned()))?,
+            None => RuleSet::default(),
+        };
+        let Some(position) = self.tag("Position") else {
+            return Ok(Game::with_rules(rules));
+        };
+        let game = Game::from_position_string(position).map_err(RecordError::InvalidPosition)?;
+        if game.rules != rules {
+            return Err(RecordError::ConflictingRules);
+        }
+        Ok(game)
+    }
+
+    /// Replays the recorded plays from the game given by the tags and returns the resulting game,
+    /// or why it can't be replayed.
+    ///
+    /// See [`GameRecord::initial_game`] and [`Game::replay`].
+    pub fn replay(&self) -> Result<Game, RecordError> {
+        let mut game = self.initial_game()?;
+        game.replay(self.plays.iter().copied())
+            .map_err(RecordError::InvalidPlay)?;
+        Ok(game)
+    }
+}
+
+impl From<&GameHistory> for GameRecord {
+    /// Records the plays that are currently applied in the given history.
+    ///
+    /// The rules are recorded in a `Rules` tag unless they're the default ones, and the game the
+    /// history started from in a `Position` tag unless it's an empty board, so that replaying
+    /// the record gives back the current game of the history.
+    fn from(history: &GameHistory) -> Self {
+        let mut record = Self {
+            tags: Vec::new(),
+            plays: history.plays()[..history.ply()].to_vec(),
+        };
+        let initial_game = history.initial_game();
+        if initial_game.rules != RuleSet::default() {
+            record.set_tag("Rules", initial_game.rules.to_string());
+        }
+        if initial_game != Game::with_rules(initial_game.rules) {
+            record.set_tag("Position", initial_game.to_position_string());
+        }
+        record
+    }
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.tags {
+            f.write_char('[')?;
+            f.write_str(key)?;
+            f.write_str(" \"")?;
+            for c in value.chars() {
+                if matches!(c, '"' | '\\') {
+                    f.write_char('\\')?;
+                }
+                f.write_char(c)?;
+            }
+            f.write_str("\"]\n")?;
+        }
+        if !self.tags.is_empty() {
+            f.write_char('\n')?;
+        }
+
+        for (i, pair) in self.plays.chunks(2).enumerate() {
+            if i > 0 {
+                f.write_char(' ')?;
+            }
+            write!(f, "{}.", i + 1)?;
+            for &play in pair {
+                write!(f, " {}", PlayNotation(play))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = ParseNotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut record = Self::new();
+        let mut lines = s.lines().map(str::trim).peekable();
+
+        while let Some(line) = lines.next_if(|line| line.is_empty() || line.starts_with('[')) {
+            if !line.is_empty() {
+                let tag = parse_tag(line)
+                    .ok_or_else(|| ParseNotationError::InvalidTag(line.to_owned()))?;
+                record.tags.push(tag);
+            }
+        }
+
+        let mut move_number = None;
+        for token in lines.flat_map(str::split_whitespace) {
+            let is_move_number = token
+                .strip_suffix('.')
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+            if is_move_number {
+                move_number = Some(token);
+            } else {
+                record.plays.push(token.parse::<PlayNotation>()?.0);
+                move_number = None;
+            }
+        }
+        if let Some(move_number) = move_number {
+            return Err(ParseNotationError::MissingPlay(move_number.to_owned()));
+        }
+
+        Ok(record)
+    }
+}
+
+/// Parses a `[Key "Value"]` line into its key and unescaped value.
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.strip_prefix('[')?.strip_suffix(']')?.split_once(' ')?;
+    if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.push(chars.next()?),
+            '"' => return None,
+            c => unescaped.push(c),
+        }
+    }
+    Some((key.to_owned(), unescaped))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseNotationError {
+    /// The given text isn't valid coordinates of a tile or index.
+    InvalidCoordinates(String),
+    /// The given header line isn't a well-formed `[Key "Value"]` tag.
+    InvalidTag(String),
+    /// The given move number at the end of the plays isn't followed by a play.
+    MissingPlay(String),
+}
+
+impl Display for ParseNotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCoordinates(s) => write!(f, "invalid coordinates: {s:?}"),
+            Self::InvalidTag(s) => write!(f, "invalid tag: {s:?}"),
+            Self::MissingPlay(s) => write!(f, "move number {s:?} isn't followed by a play"),
+        }
+    }
+}
+
+impl Error for ParseNotationError {}
+
+/// The reason a [`GameRecord`] can't be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// The given `Rules` tag is malformed.
+    InvalidRules(String),
+    /// The `Position` tag isn't a valid position string.
+    InvalidPosition(ParsePositionError),
+    /// The rules of the `Position` tag don't match the `Rules` tag.
+    ConflictingRules,
+    /// One of the recorded plays is invalid.
+    InvalidPlay(ReplayError),
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidRules(s) => write!(f, "invalid rules tag: {s:?}"),
+            Self::InvalidPosition(error) => write!(f, "invalid position tag: {error}"),
+            Self::ConflictingRules => {
+                write!(f, "the rules of the position tag don't match the rules tag")
+            }
+            Self::InvalidPlay(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for RecordError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidPosition(error) => Some(error),
+            Self::InvalidPlay(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{all_indices, all_plays, random_plays, rule_sets},
+        GameMode,
+    };
+
+    #[test]
+    fn play_notation_round_trip() {
+        for play in all_plays() {
+            let notation = PlayNotation(play).to_string();
+            assert_eq!(notation.parse(), Ok(PlayNotation(play)));
+        }
+        assert_eq!(
+            PlayNotation((BoardIndex::UpperLeft, BoardIndex::UpperLeft)).to_string(),
+            "a1"
+        );
+        assert_eq!(
+            PlayNotation((BoardIndex::Center, BoardIndex::Center)).to_string(),
+            "e5"
+        );
+        assert_eq!(
+            PlayNotation((BoardIndex::LowerRight, BoardIndex::LowerRight)).to_string(),
+            "i9"
+        );
+    }
+
+    #[test]
+    fn board_index_round_trip() {
+        for index in all_indices() {
+            assert_eq!(index.to_string().parse(), Ok(index));
+        }
+        assert_eq!("c3".parse(), Ok(BoardIndex::LowerRight));
+    }
+
+    #[test]
+    fn invalid_coordinates() {
+        for s in [
+            "", "a", "e", "a0", "a10", "j1", "A1", "1a", "e5 ", " e5", "e5.", "e5,",
+        ] {
+            assert_eq!(
+                s.parse::<PlayNotation>(),
+                Err(ParseNotationError::InvalidCoordinates(s.to_owned())),
+                "{s:?}"
+            );
+        }
+        for s in ["", "d1", "a4", "e5"] {
+            assert_eq!(
+                s.parse::<BoardIndex>(),
+                Err(ParseNotationError::InvalidCoordinates(s.to_owned())),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn game_record_round_trip() {
+        for seed in 0..20 {
+            let mut record = GameRecord::new();
+            record.set_tag("Circle", "Alice");
+            record.set_tag("Cross", "Bob \"the \\ builder\"");
+            record.plays = random_plays(RuleSet::default(), seed);
+            let text = record.to_string();
+            assert_eq!(text.parse(), Ok(record.clone()), "{text}");
+            assert!(record.replay().is_ok());
+        }
+        assert_eq!("".parse(), Ok(GameRecord::new()));
+    }
+
+    #[test]
+    fn game_record_format() {
+        let record: GameRecord = "[Circle \"Alice\"]\n\n1. e5 e4 2. e2 e6 3. e8"
+            .parse()
+            .unwrap();
+        assert_eq!(record.tag("Circle"), Some("Alice"));
+        assert_eq!(record.plays.len(), 5);
+        assert_eq!(
+            record.to_string(),
+            "[Circle \"Alice\"]\n\n1. e5 e4 2. e2 e6 3. e8"
+        );
+        let without_numbers: GameRecord = "e5 e4 e2 e6 e8".parse().unwrap();
+        assert_eq!(without_numbers.plays, record.plays);
+    }
+
+    #[test]
+    fn invalid_game_records() {
+        assert_eq!(
+            "1. e5 j4".parse::<GameRecord>(),
+            Err(ParseNotationError::InvalidCoordinates("j4".to_owned()))
+        );
+        assert_eq!(
+            "1. e5 e4 2.".parse::<GameRecord>(),
+            Err(ParseNotationError::MissingPlay("2.".to_owned()))
+        );
+        assert_eq!(
+            "1. e5, e4".parse::<GameRecord>(),
+            Err(ParseNotationError::InvalidCoordinates("e5,".to_owned()))
+        );
+        for line in [
+            "[Circle Alice]",
+            "[Circle \"Alice]",
+            "[ \"Alice\"]",
+            "[Circle \"A\"lice\"]",
+        ] {
+            assert_eq!(
+                format!("{line}\n\n1. e5").parse::<GameRecord>(),
+                Err(ParseNotationError::InvalidTag(line.to_owned())),
+                "{line:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn record_from_history() {
+        for rules in rule_sets() {
+            let plays = random_plays(rules, 4);
+            let mut history = GameHistory::from(Game::with_rules(rules));
+            for &play in &plays[..10] {
+                history.mark_tile(play);
+            }
+            let record = GameRecord::from(&history);
+            assert_eq!(record.tag("Position"), None);
+            assert_eq!(
+                record.tag("Rules"),
+                (rules != RuleSet::default())
+                    .then(|| rules.to_string())
+                    .as_deref()
+            );
+            assert_eq!(record.replay().as_ref(), Ok(history.game()));
+
+            // A history started mid-game records its starting position.
+            let mut history = GameHistory::from(history.game().clone());
+            for &play in &plays[10..] {
+                history.mark_tile(play);
+            }
+            history.seek(3);
+            let record: GameRecord = GameRecord::from(&history).to_string().parse().unwrap();
+            assert!(record.tag("Position").is_some());
+            assert_eq!(record.plays.len(), 3);
+            assert_eq!(record.replay().as_ref(), Ok(history.game()));
+        }
+    }
+
+    #[test]
+    fn invalid_record_tags() {
+        let mut record: GameRecord = "[Rules \"house\"]\n\n1. e5".parse().unwrap();
+        assert_eq!(
+            record.replay(),
+            Err(RecordError::InvalidRules("house".to_owned()))
+        );
+        record.set_tag("Rules", "misere");
+        assert_eq!(
+            record.replay().map(|game| game.rules.mode),
+            Ok(GameMode::Misere)
+        );
+
+        record.set_tag("Position", "9/9/9/9/4o4/9/9/9/9 x b2");
+        assert_eq!(record.replay(), Err(RecordError::ConflictingRules));
+        record.set_tag("Position", "9/9/9/9/4o4/9/9/9/9 x b2 misere");
+        assert!(matches!(
+            record.replay(),
+            Err(RecordError::InvalidPlay(ReplayError { ply: 0, .. }))
+        ));
+        record.set_tag("Position", "9/9/9/9/4o4/9/9/9/9 x");
+        assert_eq!(
+            record.replay(),
+            Err(RecordError::InvalidPosition(
+                ParsePositionError::WrongNumberOfFields
+            ))
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{ai::Playable, BoardState};
+
+/// Returns the number of different sequences of exactly `depth` valid plays from the given game.
+///
+/// Games that are over before `depth` plays have been made don't count, so this is the number of
+/// nodes `depth` levels below the root of the game tree.
+///
+/// The counts from the empty board are fixed by the rules, so every game representation that
+/// implements [`Playable`] must give the same numbers, which makes them a test of the rules as
+/// well as a benchmark of move generation.
+pub fn perft<G: Playable>(game: &G, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if !matches!(game.state(), BoardState::InProgress) {
+        return 0;
+    }
+    if depth == 1 {
+        return game.legal_moves().count() as u64;
+    }
+
+    game.legal_moves()
+        .map(|play| {
+            let mut game = game.clone();
+            game.mark_tile(play);
+            perft(&game, depth - 1)
+        })
+        .sum()
+}
+
+/// Returns the [`perft`] count below every valid play from the given game, in the order of
+/// [`Playable::legal_moves`].
+///
+/// Comparing these with the counts of another implementation narrows down where they disagree.
+pub fn perft_divide<G: Playable>(game: &G, depth: usize) -> Vec<(G::Play, u64)> {
+    game.legal_moves()
+        .map(|play| {
+            let mut game = game.clone();
+            game.mark_tile(play);
+            (play, perft(&game, depth.saturating_sub(1)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FastGame, Game, NestedGame};
+
+    /// The number of leaves of the game tree from the empty board at depths 0, 1, 2 and so on.
+    const EMPTY_BOARD: [u64; 6] = [1, 81, 720, 6336, 55080, 473256];
+
+    #[test]
+    fn perft_empty_board() {
+        for (depth, &expected) in EMPTY_BOARD.iter().enumerate() {
+            assert_eq!(perft(&Game::new(), depth), expected, "depth {depth}");
+        }
+    }
+
+    #[test]
+    fn perft_matches_across_representations() {
+        for (depth, &expected) in EMPTY_BOARD.iter().enumerate().take(5) {
+            assert_eq!(perft(&FastGame::new(), depth), expected, "depth {depth}");
+            assert_eq!(perft(&NestedGame::new(2), depth), expected, "depth {depth}");
+        }
+    }
+
+    #[test]
+    fn perft_divide_sums_to_perft() {
+        let divide = perft_divide(&Game::new(), 3);
+        assert_eq!(divide.len(), 81);
+        assert_eq!(divide.iter().map(|&(_, n)| n).sum::<u64>(), EMPTY_BOARD[3]);
+    }
+}
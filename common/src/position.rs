@@ -0,0 +1,542 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Write},
+};
+
+use crate::{
+    notation::{grid_coordinates, play_at},
+    Board, BoardIndex, BoardOutcome, BoardState, Game, InvalidGame, Player, Region, RuleSet, Tile,
+};
+
+impl Game {
+    /// Returns the position string of this game.
+    ///
+    /// A position string is a single line with three to five space-separated fields, similar to
+    /// FEN:
+    ///
+    /// 1. The tiles of the whole 9x9 grid, row by row from the top with rows separated by `/`.
+    ///    Each row lists its tiles from the left as `o` for [`Player::Circle`], `x` for
+    ///    [`Player::Cross`], and a digit for that many consecutive unmarked tiles.
+    /// 2. The player to move, `o` or `x`.
+    /// 3. The previous play index in [`BoardIndex`] notation, or `-` if there's none.
+    /// 4. The rules as written by the [`Display`] implementation of [`RuleSet`], left out for the
+    ///    default rules.
+    /// 5. The regions with a three-in-a-line of both players that were won by Cross, separated by
+    ///    commas, left out if there are none. Such regions only happen under
+    ///    [`RuleSet::won_regions_playable`], and who won them first can't be told from their tiles.
+    ///
+    /// For example, the position after the center tile of the center region has been marked is
+    /// `9/9/9/9/4o4/9/9/9/9 x b2`, and `9/9/9/9/4o4/9/9/9/9 x b2 misere` with misère rules.
+    pub fn to_position_string(&self) -> String {
+        let mut grid = [[Tile::Unmarked; 9]; 9];
+        for (region_index, region) in self.board.enumerate() {
+            for (tile_index, &tile) in region.board.enumerate() {
+                let (row, column) = grid_coordinates((region_index, tile_index));
+                grid[row][column] = tile;
+            }
+        }
+
+        let mut s = String::new();
+        for (i, row) in grid.iter().enumerate() {
+            if i > 0 {
+                s.push('/');
+            }
+            let mut n_unmarked = 0;
+            for tile in row {
+                match tile {
+                    Tile::Unmarked => n_unmarked += 1,
+                    Tile::Marked(player) => {
+                        if n_unmarked > 0 {
+                            write!(s, "{n_unmarked}").unwrap();
+                            n_unmarked = 0;
+                        }
+                        s.push(player_char(*player));
+                    }
+                }
+            }
+            if n_unmarked > 0 {
+                write!(s, "{n_unmarked}").unwrap();
+            }
+        }
+
+        s.push(' ');
+        s.push(player_char(self.current_player));
+        s.push(' ');
+        match self.previous_play_index {
+            Some(index) => write!(s, "{index}").unwrap(),
+            None => s.push('-'),
+        }
+        let cross_won_regions = self.cross_won_regions_of_both();
+        if self.rules != RuleSet::default() || !cross_won_regions.is_empty() {
+            write!(s, " {}", self.rules).unwrap();
+        }
+        if !cross_won_regions.is_empty() {
+            s.push(' ');
+            write_index_list(&mut s, &cross_won_regions).unwrap();
+        }
+        s
+    }
+
+    /// Returns the regions with a three-in-a-line of both players that were won by Cross.
+    pub(crate) fn cross_won_regions_of_both(&self) -> Vec<BoardIndex> {
+        self.board
+            .enumerate()
+            .filter(|(_, region)| {
+                region.has_lines_of_both()
+                    && region.state == BoardState::Complete(BoardOutcome::WonBy(Player::Cross))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Parses a position string as described in [`Game::to_position_string`].
+    ///
+    /// The states of the regions and the game are recomputed from the tiles with the given rules,
+    /// or the default ones if they're left out, and positions that can't be reached in a game are
+    /// rejected.
+    pub fn from_position_string(s: &str) -> Result<Self, ParsePositionError> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [tiles, current_player, previous_play_index, ref optional_fields @ ..] = fields[..]
+        else {
+            return Err(ParsePositionError::WrongNumberOfFields);
+        };
+        let (rules, cross_won_regions) = match optional_fields {
+            [] => (RuleSet::default(), None),
+            [rules] => (parse_rules(rules)?, None),
+            [rules, regions] => (parse_rules(rules)?, Some(*regions)),
+            _ => return Err(ParsePositionError::WrongNumberOfFields),
+        };
+
+        let mut board = Board::<Region>::default();
+        let rows: Vec<&str> = tiles.split('/').collect();
+        if rows.len() != 9 {
+            return Err(ParsePositionError::InvalidTiles(tiles.to_owned()));
+        }
+        for (row, text) in rows.into_iter().enumerate() {
+            let invalid_row = || ParsePositionError::InvalidTiles(text.to_owned());
+            let mut column = 0;
+            for c in text.chars() {
+                let tile = match c {
+                    '1'..='9' => {
+                        column += c as usize - '0' as usize;
+                        continue;
+                    }
+                    c => Tile::Marked(parse_player(c).ok_or_else(invalid_row)?),
+                };
+                let (region_index, tile_index) = play_at(row, column).ok_or_else(invalid_row)?;
+                board[region_index].board[tile_index] = tile;
+                column += 1;
+            }
+            if column != 9 {
+                return Err(invalid_row());
+            }
+        }
+
+        let mut chars = current_player.chars();
+        let current_player = match (chars.next(), chars.next()) {
+            (Some(c), None) => parse_player(c),
+            _ => None,
+        }
+        .ok_or_else(|| ParsePositionError::InvalidPlayer(current_player.to_owned()))?;
+
+        let previous_play_index = match previous_play_index {
+            "-" => None,
+            s => Some(
+                s.parse::<BoardIndex>()
+                    .map_err(|_| ParsePositionError::InvalidPreviousPlayIndex(s.to_owned()))?,
+            ),
+        };
+
+        if let Some(s) = cross_won_regions {
+            let invalid_regions = || ParsePositionError::InvalidRegionWinners(s.to_owned());
+            let regions = parse_index_list(s).ok_or_else(invalid_regions)?;
+            set_cross_won_regions(&mut board, &regions).ok_or_else(invalid_regions)?;
+        }
+        Self::from_tiles(board, current_player, previous_play_index, rules)
+    }
+
+    /// Returns the game with the given tiles, player to move, previous play index and rules, with
+    /// the states of the regions and the game recomputed from the tiles.
+    ///
+    /// The states of the given regions are ignored, except for regions with a three-in-a-line of
+    /// both players, which only [`RuleSet::won_regions_playable`] allows: who won those first can't
+    /// be told from their tiles, so they're won by Cross if their given state says so, and by
+    /// Circle otherwise. Positions that can't be reached in a game are rejected.
+    pub(crate) fn from_tiles(
+        board: Board<Region>,
+        current_player: Player,
+        previous_play_index: Option<BoardIndex>,
+        rules: RuleSet,
+    ) -> Result<Self, ParsePositionError> {
+        let mut game = Game {
+            board,
+            state: BoardState::InProgress,
+            current_player,
+            previous_play_index,
+            rules,
+            key: 0,
+        };
+        game.recompute_states()?;
+        game.update_key();
+        Ok(game)
+    }
+
+    /// Recomputes the state of every region and of the game from the tiles, checking that the
+    /// tiles, the current player and the previous play index could have been reached in a game.
+    fn recompute_states(&mut self) -> Result<(), ParsePositionError> {
+        for region in &mut self.board.tiles {
+            region.state = if !region.has_lines_of_both() {
+                region.board.get_state()
+            } else if region.state == BoardState::Complete(BoardOutcome::WonBy(Player::Cross)) {
+                region.state
+            } else {
+                BoardState::Complete(BoardOutcome::WonBy(Player::Circle))
+            };
+        }
+        self.state = self
+            .rules
+            .get_state(&self.board, self.current_player.other());
+        Ok(self.validate()?)
+    }
+}
+
+impl Region {
+    /// Check if both players have a three-in-a-line in this region.
+    pub(crate) fn has_lines_of_both(&self) -> bool {
+        self.board.check_player(Player::Circle) && self.board.check_player(Player::Cross)
+    }
+}
+
+/// Marks the given regions as won by Cross, or returns `None` if one of them doesn't have a
+/// three-in-a-line of both players.
+pub(crate) fn set_cross_won_regions(
+    board: &mut Board<Region>,
+    regions: &[BoardIndex],
+) -> Option<()> {
+    for &index in regions {
+        let region = &mut board[index];
+        if !region.has_lines_of_both() {
+            return None;
+        }
+        region.state = BoardState::Complete(BoardOutcome::WonBy(Player::Cross));
+    }
+    Some(())
+}
+
+/// Writes the given indices in [`BoardIndex`] notation, separated by commas.
+pub(crate) fn write_index_list(w: &mut impl Write, indices: &[BoardIndex]) -> fmt::Result {
+    for (i, index) in indices.iter().enumerate() {
+        if i > 0 {
+            w.write_char(',')?;
+        }
+        write!(w, "{index}")?;
+    }
+    Ok(())
+}
+
+/// Parses distinct indices in [`BoardIndex`] notation separated by commas.
+pub(crate) fn parse_index_list(s: &str) -> Option<Vec<BoardIndex>> {
+    let mut indices = Vec::new();
+    for index in s.split(',') {
+        let index = index.parse().ok()?;
+        if indices.contains(&index) {
+            return None;
+        }
+        indices.push(index);
+    }
+    Some(indices)
+}
+
+pub(crate) fn parse_rules(s: &str) -> Result<RuleSet, ParsePositionError> {
+    s.parse()
+        .map_err(|_| ParsePositionError::InvalidRules(s.to_owned()))
+}
+
+pub(crate) fn player_char(player: Player) -> char {
+    match player {
+        Player::Circle => 'o',
+        Player::Cross => 'x',
+    }
+}
+
+pub(crate) fn parse_player(c: char) -> Option<Player> {
+    match c {
+        'o' => Some(Player::Circle),
+        'x' => Some(Player::Cross),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePositionError {
+    /// The position string doesn't have three to five fields.
+    WrongNumberOfFields,
+    /// The given row or tiles field is malformed.
+    InvalidTiles(String),
+    /// The given player to move is neither `o` nor `x`.
+    InvalidPlayer(String),
+    /// The given previous play index is malformed.
+    InvalidPreviousPlayIndex(String),
+    /// The given rules are malformed.
+    InvalidRules(String),
+    /// The given regions won by Cross are malformed, or one of them doesn't have a three-in-a-line
+    /// of both players.
+    InvalidRegionWinners(String),
+    /// The position can't be reached in a game.
+    InvalidPosition(InvalidGame),
+    /// The given status line of a diagram is malformed or doesn't match the tiles.
+    InvalidStatus(String),
+    /// The binary encoding of a position is out of range.
+    InvalidEncoding,
+}
+
+impl Display for ParsePositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongNumberOfFields => write!(f, "a position should have three to five fields"),
+            Self::InvalidTiles(s) => write!(f, "invalid tiles: {s:?}"),
+            Self::InvalidPlayer(s) => write!(f, "invalid player to move: {s:?}"),
+            Self::InvalidPreviousPlayIndex(s) => write!(f, "invalid previous play index: {s:?}"),
+            Self::InvalidRules(s) => write!(f, "invalid rules: {s:?}"),
+            Self::InvalidRegionWinners(s) => write!(f, "invalid regions won by cross: {s:?}"),
+            Self::InvalidPosition(error) => write!(f, "invalid position: {error}"),
+            Self::InvalidStatus(s) => write!(f, "invalid status: {s:?}"),
+            Self::InvalidEncoding => write!(f, "the encoded position is out of range"),
+        }
+    }
+}
+
+impl Error for ParsePositionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidPosition(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<InvalidGame> for ParsePositionError {
+    fn from(error: InvalidGame) -> Self {
+        Self::InvalidPosition(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{random_positions, rule_sets},
+        GameMode,
+    };
+
+    const EMPTY_TILES: &str = "9/9/9/9/9/9/9/9/9";
+
+    #[test]
+    fn position_string_round_trip() {
+        for rules in rule_sets() {
+            for seed in 0..20 {
+                for game in random_positions(rules, seed) {
+                    let s = game.to_position_string();
+                    assert_eq!(Game::from_position_string(&s), Ok(game), "{s}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn position_string_format() {
+        let mut game = Game::default();
+        assert_eq!(game.to_position_string(), "9/9/9/9/9/9/9/9/9 o -");
+        game.try_mark_tile((BoardIndex::Center, BoardIndex::Center))
+            .unwrap();
+        assert_eq!(game.to_position_string(), "9/9/9/9/4o4/9/9/9/9 x b2");
+        game.try_mark_tile((BoardIndex::Center, BoardIndex::UpperLeft))
+            .unwrap();
+        assert_eq!(game.to_position_string(), "9/9/9/3x5/4o4/9/9/9/9 o a1");
+
+        game.rules.mode = GameMode::Misere;
+        assert_eq!(
+            game.to_position_string(),
+            "9/9/9/3x5/4o4/9/9/9/9 o a1 misere"
+        );
+    }
+
+    #[test]
+    fn regions_won_by_cross() {
+        let rules = RuleSet {
+            won_regions_playable: true,
+            ..Default::default()
+        };
+        let game = (0..)
+            .flat_map(|seed| random_positions(rules, seed))
+            .find(|game| !game.cross_won_regions_of_both().is_empty())
+            .unwrap();
+        let s = game.to_position_string();
+        let (without_winners, winners) = s.rsplit_once(' ').unwrap();
+        assert!(without_winners.ends_with(" won-regions-playable"), "{s}");
+        assert_eq!(
+            parse_index_list(winners),
+            Some(game.cross_won_regions_of_both())
+        );
+        assert_eq!(Game::from_position_string(&s), Ok(game.clone()));
+
+        // Without the winners, the regions are won by Circle instead.
+        let region_index = game.cross_won_regions_of_both()[0];
+        match Game::from_position_string(without_winners) {
+            Ok(other) => assert_eq!(
+                other.board[region_index].state,
+                BoardState::Complete(BoardOutcome::WonBy(Player::Circle))
+            ),
+            Err(error) => assert!(
+                matches!(error, ParsePositionError::InvalidPosition(_)),
+                "{error:?}"
+            ),
+        }
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for rules in ["-", "misere,misere", "normal"] {
+            let s = format!("{EMPTY_TILES} o - {rules}");
+            assert_eq!(
+                Game::from_position_string(&s),
+                Err(ParsePositionError::InvalidRules(rules.to_owned())),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_region_winners() {
+        for regions in ["a1,a1", "d1", "-", "b1"] {
+            let s = format!("ooo6/9/9/9/9/9/9/9/9 x a1 won-regions-playable {regions}");
+            assert_eq!(
+                Game::from_position_string(&s),
+                Err(ParsePositionError::InvalidRegionWinners(regions.to_owned())),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_number_of_fields() {
+        for s in [
+            "",
+            EMPTY_TILES,
+            "9/9/9/9/9/9/9/9/9 o",
+            "9/9/9/9/9/9/9/9/9 o - standard a1 b2",
+        ] {
+            assert_eq!(
+                Game::from_position_string(s),
+                Err(ParsePositionError::WrongNumberOfFields),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_tiles() {
+        assert_eq!(
+            Game::from_position_string("9/9/9/9/9/9/9/9 o -"),
+            Err(ParsePositionError::InvalidTiles(
+                "9/9/9/9/9/9/9/9".to_owned()
+            ))
+        );
+        for (s, row) in [
+            ("9/9/9/9/8/9/9/9/9 o -", "8"),
+            ("9/9/9/9/4o5/9/9/9/9 x b2", "4o5"),
+            ("9/9/9/9/4q4/9/9/9/9 x b2", "4q4"),
+            ("9/9/9/9/0o8/9/9/9/9 x b2", "0o8"),
+        ] {
+            assert_eq!(
+                Game::from_position_string(s),
+                Err(ParsePositionError::InvalidTiles(row.to_owned())),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_player() {
+        for player in ["z", "ox", "O"] {
+            let s = format!("{EMPTY_TILES} {player} -");
+            assert_eq!(
+                Game::from_position_string(&s),
+                Err(ParsePositionError::InvalidPlayer(player.to_owned())),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_previous_play_index() {
+        for index in ["d4", "a", "b22", "--"] {
+            let s = format!("9/9/9/9/4o4/9/9/9/9 x {index}");
+            assert_eq!(
+                Game::from_position_string(&s),
+                Err(ParsePositionError::InvalidPreviousPlayIndex(
+                    index.to_owned()
+                )),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_position() {
+        for (s, error) in [
+            (
+                "9/9/9/9/4o4/9/9/9/9 o b2",
+                InvalidGame::InvalidMarkCounts {
+                    n_circle: 1,
+                    n_cross: 0,
+                },
+            ),
+            (
+                "9/9/9/9/3xx4/9/9/9/9 o b2",
+                InvalidGame::InvalidMarkCounts {
+                    n_circle: 0,
+                    n_cross: 2,
+                },
+            ),
+            (
+                "9/9/9/9/4o4/9/9/9/9 x a1",
+                InvalidGame::InvalidPreviousPlayIndex(Some(BoardIndex::UpperLeft)),
+            ),
+            (
+                "9/9/9/9/4o4/9/9/9/9 x -",
+                InvalidGame::InvalidPreviousPlayIndex(None),
+            ),
+            (
+                "ooo6/xxx6/9/9/9/9/9/9/9 o a1",
+                InvalidGame::RegionWonByBoth(BoardIndex::UpperLeft),
+            ),
+        ] {
+            assert_eq!(
+                Game::from_position_string(s),
+                Err(ParsePositionError::InvalidPosition(error)),
+                "{s:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_status() {
+        let diagram = Game::default().to_string();
+        assert!(diagram.parse::<Game>().is_ok());
+        for status in [
+            "o won",
+            "draw",
+            "q to move",
+            "o to play",
+            "o to move, previous index z9",
+        ] {
+            let diagram = diagram.replace("o to move", status);
+            assert_eq!(
+                diagram.parse::<Game>(),
+                Err(ParsePositionError::InvalidStatus(status.to_owned())),
+                "{status:?}"
+            );
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{Board, BoardIndex, BoardItem, BoardOutcome, BoardState, MarkTileResult, Player, Tile};
+use crate::{
+    validate::InvalidRegionState, Board, BoardIndex, BoardItem, BoardOutcome, BoardState,
+    MarkTileResult, Player, RuleSet, Tile,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+#[serde(try_from = "RegionFields")]
 pub struct Region {
     pub board: Board<Tile>,
     pub state: BoardState,
@@ -12,7 +16,7 @@ impl Region {
     /// Mark the specified tile with the specified player,
     /// then update the state of the region accordingly.
     pub fn mark_tile(&mut self, index: BoardIndex, player: Player) -> MarkTileResult {
-        if !self.is_tile_enabled(index) {
+        if !self.is_markable() || !self.board[index].is_markable() {
             return MarkTileResult::NoChange;
         }
 
@@ -27,8 +31,10 @@ impl Region {
         }
     }
 
-    pub fn is_tile_enabled(&self, index: BoardIndex) -> bool {
-        self.is_markable() && self.board[index].is_markable()
+    /// Check if the given tile can be marked under the given rules, provided the region itself
+    /// is enabled according to [`Game::is_region_enabled`](crate::Game::is_region_enabled).
+    pub fn is_tile_enabled(&self, index: BoardIndex, rules: &RuleSet) -> bool {
+        rules.is_region_open(self) && self.board[index].is_markable()
     }
 }
 
@@ -41,3 +47,23 @@ impl BoardItem for Region {
         matches!(self.state, BoardState::InProgress)
     }
 }
+
+/// The serialized fields of a [`Region`], which are validated when deserializing.
+#[derive(Deserialize)]
+struct RegionFields {
+    board: Board<Tile>,
+    state: BoardState,
+}
+
+impl TryFrom<RegionFields> for Region {
+    type Error = InvalidRegionState;
+
+    fn try_from(fields: RegionFields) -> Result<Self, Self::Error> {
+        let region = Self {
+            board: fields.board,
+            state: fields.state,
+        };
+        region.validate()?;
+        Ok(region)
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    iter::FusedIterator,
+};
+
+use crate::{notation::PlayNotation, Game, IllegalMove, Play};
+
+impl Game {
+    /// Makes the given plays one by one from an empty board with the default rules and returns
+    /// the resulting game, or the first play that is invalid.
+    pub fn from_moves(plays: &[Play]) -> Result<Self, ReplayError> {
+        let mut game = Self::new();
+        game.replay(plays.iter().copied())?;
+        Ok(game)
+    }
+
+    /// Makes the given plays one by one in this game.
+    ///
+    /// Stops at the first play that is invalid and returns it, leaving the game as it was after
+    /// the plays before it. The ply in the error counts from this game, not from the start of
+    /// the game.
+    pub fn replay(&mut self, plays: impl IntoIterator<Item = Play>) -> Result<(), ReplayError> {
+        for (ply, play) in plays.into_iter().enumerate() {
+            self.try_mark_tile(play)
+                .map_err(|error| ReplayError { ply, play, error })?;
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over the games after each of the given plays, made one by one from
+    /// this game.
+    ///
+    /// This game itself isn't included. The iterator yields the first invalid play as an error,
+    /// then stops.
+    pub fn positions<I: IntoIterator<Item = Play>>(&self, plays: I) -> Positions<I::IntoIter> {
+        Positions {
+            game: Some(self.clone()),
+            plays: plays.into_iter().enumerate(),
+        }
+    }
+}
+
+/// An iterator over the games after each play in a list, created by [`Game::positions`].
+#[derive(Debug, Clone)]
+pub struct Positions<I> {
+    /// The game after the plays yielded so far, or `None` once an invalid play has been found.
+    game: Option<Game>,
+    plays: std::iter::Enumerate<I>,
+}
+
+impl<I: Iterator<Item = Play>> Iterator for Positions<I> {
+    type Item = Result<Game, ReplayError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let game = self.game.as_mut()?;
+        let Some((ply, play)) = self.plays.next() else {
+            self.game = None;
+            return None;
+        };
+        match game.try_mark_tile(play) {
+            Ok(_) => Some(Ok(game.clone())),
+            Err(error) => {
+                self.game = None;
+                Some(Err(ReplayError { ply, play, error }))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.game {
+            Some(_) => (0, self.plays.size_hint().1),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Play>> FusedIterator for Positions<I> {}
+
+/// The error returned when replaying a list of plays that contains an invalid play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayError {
+    /// The number of plays made before the invalid play.
+    pub ply: usize,
+    /// The invalid play.
+    pub play: Play,
+    /// Why the play is invalid.
+    pub error: IllegalMove,
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "play {} ({}) is invalid: {}",
+            self.ply + 1,
+            PlayNotation(self.play),
+            self.error
+        )
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{random_plays, random_positions},
+        BoardIndex, GameRecord, RuleSet,
+    };
+
+    #[test]
+    fn replay_reproduces_recorded_game() {
+        for seed in 0..20 {
+            let positions = random_positions(RuleSet::default(), seed);
+            let mut record = GameRecord::new();
+            record.plays = random_plays(RuleSet::default(), seed);
+            let record: GameRecord = record.to_string().parse().unwrap();
+
+            let game = record.replay().unwrap();
+            assert_eq!(Some(&game), positions.last());
+            assert_eq!(game.key(), positions.last().unwrap().key());
+            let replayed: Result<Vec<Game>, _> = Game::new()
+                .positions(record.plays.iter().copied())
+                .collect();
+            assert_eq!(replayed.as_deref(), Ok(&positions[1..]));
+        }
+    }
+
+    #[test]
+    fn replay_known_game() {
+        let record: GameRecord = "1. e5 e4 2. e2 e6 3. e8 d5".parse().unwrap();
+        let game = record.replay().unwrap();
+        assert_eq!(
+            game.to_position_string(),
+            "9/4o4/9/4x4/3xo4/4x4/9/4o4/9 o a2"
+        );
+        assert_eq!(game.allowed_region_index(), Some(BoardIndex::Left));
+    }
+
+    #[test]
+    fn replay_stops_at_illegal_play() {
+        let e5 = (BoardIndex::Center, BoardIndex::Center);
+        let a1 = (BoardIndex::UpperLeft, BoardIndex::UpperLeft);
+        for (plays, ply, play, error) in [
+            (vec![e5, e5], 1, e5, IllegalMove::TileOccupied),
+            (
+                vec![e5, a1],
+                1,
+                a1,
+                IllegalMove::WrongRegion {
+                    required: BoardIndex::Center,
+                },
+            ),
+        ] {
+            let expected = ReplayError { ply, play, error };
+            assert_eq!(Game::from_moves(&plays), Err(expected));
+            let mut game = Game::new();
+            assert_eq!(game.replay(plays.iter().copied()), Err(expected));
+            assert_eq!(Game::from_moves(&plays[..ply]), Ok(game.clone()));
+
+            let mut positions = Game::new().positions(plays);
+            for _ in 0..ply {
+                assert!(matches!(positions.next(), Some(Ok(_))));
+            }
+            assert_eq!(positions.next(), Some(Err(expected)));
+            assert_eq!(positions.next(), None);
+        }
+
+        for seed in 0..10 {
+            let mut plays = random_plays(RuleSet::default(), seed);
+            let ply = plays.len();
+            plays.push(a1);
+            assert_eq!(
+                Game::from_moves(&plays),
+                Err(ReplayError {
+                    ply,
+                    play: a1,
+                    error: IllegalMove::GameOver,
+                })
+            );
+        }
+    }
+}
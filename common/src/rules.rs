@@ -0,0 +1,289 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Write},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Geometry, Grid, Standard},
+    Board, BoardItem, BoardOutcome, BoardState, Player, Region,
+};
+
+/// The house rules a [`Game`](crate::Game) is played with.
+///
+/// The default rule set is the standard game: a player sent to a region that can't be played in
+/// anymore may play in any region, won and drawn regions are closed, drawn regions count for
+/// nobody, and the game is drawn when every region is closed without three regions in a line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    /// Drawn regions count as won by both players when checking for three regions in a line.
+    pub drawn_regions_count_for_both: bool,
+    /// Won regions can still be played in until they're full.
+    ///
+    /// Playing in a won region never changes who won it.
+    pub won_regions_playable: bool,
+    /// When the game would be drawn, the player who won more regions wins instead.
+    pub region_count_tiebreak: bool,
+    /// The first play of the game may not be in the center region.
+    pub no_center_first_move: bool,
+    /// Whether three regions in a line win or lose the game.
+    pub mode: GameMode,
+}
+
+/// Whether the goal of a game is to get three regions in a line or to avoid it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Completing three regions in a line wins the game.
+    #[default]
+    Normal,
+    /// Completing three regions in a line loses the game.
+    ///
+    /// With [`RuleSet::region_count_tiebreak`], the player who won fewer regions wins instead.
+    Misere,
+}
+
+impl GameMode {
+    /// Returns the winner of a game in which the given player has three regions in a line.
+    pub fn line_winner(self, player: Player) -> Player {
+        match self {
+            GameMode::Normal => player,
+            GameMode::Misere => player.other(),
+        }
+    }
+}
+
+impl RuleSet {
+    /// Check if plays can still be made in the given region.
+    pub fn is_region_open(&self, region: &Region) -> bool {
+        match region.state {
+            BoardState::InProgress => true,
+            BoardState::Complete(BoardOutcome::WonBy(_)) => {
+                self.won_regions_playable && region.board.unmarked().next().is_some()
+            }
+            BoardState::Complete(BoardOutcome::Draw) => false,
+        }
+    }
+
+    /// Check if the given region counts as won by the given player when checking for three
+    /// regions in a line.
+    pub fn is_region_marked_by(&self, region: &Region, player: Player) -> bool {
+        match region.state {
+            BoardState::Complete(BoardOutcome::Draw) => self.drawn_regions_count_for_both,
+            _ => region.is_marked_by(player),
+        }
+    }
+
+    /// Get the state of a game with the given regions.
+    ///
+    /// `last_player` is the player who made the last play. If both players have three regions in
+    /// a line, which can only happen when drawn regions count for both, the line counts for the
+    /// last player since they completed it.
+    pub fn get_state(&self, board: &Board<Region>, last_player: Player) -> BoardState {
+        self.get_state_in(&Standard, board, last_player)
+    }
+
+    /// Get the state of a game with the given regions when lines of regions are `line_length`
+    /// regions long.
+    ///
+    /// See [`RuleSet::get_state`]. Panics if `line_length` is 0 or greater than 3.
+    pub fn get_state_with_line_length(
+        &self,
+        line_length: usize,
+        board: &Board<Region>,
+        last_player: Player,
+    ) -> BoardState {
+        self.get_state_in(Grid::standard(line_length), board, last_player)
+    }
+
+    /// Get the state of a game with the given regions when lines of regions are those of the given
+    /// geometry, which has the layout of [`Standard`].
+    fn get_state_in(
+        &self,
+        geometry: &impl Geometry,
+        board: &Board<Region>,
+        last_player: Player,
+    ) -> BoardState {
+        for player in [last_player, last_player.other()] {
+            if geometry.has_line(|index| self.is_region_marked_by(&board.tiles[index], player)) {
+                let winner = self.mode.line_winner(player);
+                return BoardState::Complete(BoardOutcome::WonBy(winner));
+            }
+        }
+
+        if board.tiles.iter().any(|region| self.is_region_open(region)) {
+            return BoardState::InProgress;
+        }
+
+        if self.region_count_tiebreak {
+            let count = |player| {
+                board
+                    .tiles
+                    .iter()
+                    .filter(|region| region.is_marked_by(player))
+                    .count()
+            };
+            let (n_circle, n_cross) = (count(Player::Circle), count(Player::Cross));
+            if n_circle != n_cross {
+                let most_regions = if n_circle > n_cross {
+                    Player::Circle
+                } else {
+                    Player::Cross
+                };
+                let winner = self.mode.line_winner(most_regions);
+                return BoardState::Complete(BoardOutcome::WonBy(winner));
+            }
+        }
+
+        BoardState::Complete(BoardOutcome::Draw)
+    }
+
+    /// The names of the rules that can differ from the default ones, in the order of the fields,
+    /// as written by the [`Display`] implementation.
+    pub const NAMES: [&'static str; 5] = [
+        "drawn-regions-count-for-both",
+        "won-regions-playable",
+        "region-count-tiebreak",
+        "no-center-first-move",
+        "misere",
+    ];
+
+    /// Returns one bit for each rule that differs from the default ones, from
+    /// [`RuleSet::drawn_regions_count_for_both`] as the least significant bit to
+    /// [`GameMode::Misere`] as the most significant one, in the order of the fields.
+    pub fn to_bits(&self) -> u32 {
+        [
+            self.drawn_regions_count_for_both,
+            self.won_regions_playable,
+            self.region_count_tiebreak,
+            self.no_center_first_move,
+            self.mode == GameMode::Misere,
+        ]
+        .iter()
+        .rev()
+        .fold(0, |bits, &bit| bits << 1 | u32::from(bit))
+    }
+
+    /// Returns the rules with the given bits, as returned by [`RuleSet::to_bits`]. Higher bits are
+    /// ignored.
+    pub fn from_bits(bits: u32) -> Self {
+        let bit = |i: u32| bits >> i & 1 == 1;
+        Self {
+            drawn_regions_count_for_both: bit(0),
+            won_regions_playable: bit(1),
+            region_count_tiebreak: bit(2),
+            no_center_first_move: bit(3),
+            mode: if bit(4) {
+                GameMode::Misere
+            } else {
+                GameMode::Normal
+            },
+        }
+    }
+}
+
+impl Display for RuleSet {
+    /// Writes the names of the rules that differ from the default ones, separated by commas, or
+    /// `standard` for the default rules, e.g. `won-regions-playable,misere`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::default() {
+            return f.write_str("standard");
+        }
+        let bits = self.to_bits();
+        let names = Self::NAMES
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| bits >> i & 1 == 1)
+            .map(|(_, name)| *name);
+        for (i, name) in names.enumerate() {
+            if i > 0 {
+                f.write_char(',')?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RuleSet {
+    type Err = ParseRulesError;
+
+    /// Parses rules as written by the [`Display`] implementation, with the names in any order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "standard" {
+            return Ok(Self::default());
+        }
+        let mut bits = 0;
+        for name in s.split(',') {
+            let i = Self::NAMES
+                .iter()
+                .position(|&other| other == name)
+                .ok_or_else(|| ParseRulesError(s.to_owned()))?;
+            if bits >> i & 1 == 1 {
+                return Err(ParseRulesError(s.to_owned()));
+            }
+            bits |= 1 << i;
+        }
+        Ok(Self::from_bits(bits))
+    }
+}
+
+/// The error returned when parsing rules that aren't a list of distinct rule names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRulesError(pub String);
+
+impl Display for ParseRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid rules: {:?}", self.0)
+    }
+}
+
+impl Error for ParseRulesError {}
+
+/// The error returned when converting a game played with rules that the target doesn't support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedRules(pub RuleSet);
+
+impl Display for UnsupportedRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the rules {:?} aren't supported", self.0)
+    }
+}
+
+impl Error for UnsupportedRules {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_round_trip() {
+        for bits in 0..1 << RuleSet::NAMES.len() {
+            let rules = RuleSet::from_bits(bits);
+            assert_eq!(rules.to_bits(), bits);
+            assert_eq!(rules.to_string().parse(), Ok(rules), "{rules}");
+        }
+        assert_eq!(RuleSet::default().to_bits(), 0);
+        assert_eq!(RuleSet::default().to_string(), "standard");
+        let rules = RuleSet {
+            won_regions_playable: true,
+            mode: GameMode::Misere,
+            ..Default::default()
+        };
+        assert_eq!(rules.to_string(), "won-regions-playable,misere");
+        assert_eq!("misere,won-regions-playable".parse(), Ok(rules));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for s in ["", "misere,", "misere,misere", "Misere", "standard,misere"] {
+            assert_eq!(
+                s.parse::<RuleSet>(),
+                Err(ParseRulesError(s.to_owned())),
+                "{s:?}"
+            );
+        }
+    }
+}
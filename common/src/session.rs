@@ -0,0 +1,615 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    clock::Untimed, BoardOutcome, BoardState, Clock, ClockState, FlagFall, Game, IllegalMove,
+    MoveOutcome, Play, Player, TimeSource,
+};
+
+/// A [`Game`] played between two players, which can also end by resignation, timeout,
+/// abandonment or agreement.
+///
+/// The outcome of the board is computed by the game as usual. The session only adds the ways a
+/// game can end off the board, and records how it ended in a [`GameResult`].
+///
+/// A session started with [`GameSession::with_clock`] carries a [`Clock`] measured with the
+/// time source `S`, which is pressed after every play. Sessions without a clock use the
+/// [`Untimed`] time source.
+///
+/// A session is serialized as its [`SessionState`]. Untimed sessions can be deserialized directly,
+/// and sessions measured with another time source are restored with [`GameSession::from_state`].
+/// Either way, the session is checked to be consistent, see [`InvalidSession`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(into = "SessionState", bound(serialize = "S: TimeSource + Clone"))]
+pub struct GameSession<S = Untimed> {
+    game: Game,
+    result: Option<GameResult>,
+    /// The player who offered a draw that hasn't been answered yet.
+    draw_offer: Option<Player>,
+    clock: Option<Clock<S>>,
+}
+
+/// How a finished game ended and why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameResult {
+    pub outcome: BoardOutcome,
+    pub termination: Termination,
+}
+
+/// The reason a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Termination {
+    /// The game was decided on the board.
+    Board,
+    /// A player resigned.
+    Resignation,
+    /// A player ran out of time.
+    Timeout,
+    /// A player left the game.
+    Abandonment,
+    /// The players agreed to a draw.
+    Agreement,
+}
+
+impl GameSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: TimeSource> GameSession<S> {
+    /// Starts a new game played with the given clock, starting the clock of the first player.
+    pub fn with_clock(clock: Clock<S>) -> Self {
+        Self::with_game_and_clock(Game::new(), clock)
+    }
+
+    /// Starts a session from the given game played with the given clock, starting the clock of
+    /// the player to move.
+    ///
+    /// The session is already over if the game has been decided, and the clock stays stopped
+    /// then.
+    pub fn with_game_and_clock(game: Game, mut clock: Clock<S>) -> Self {
+        let mut session = Self::from(game);
+        if !session.is_over() {
+            clock.start(session.game.current_player);
+        }
+        session.clock = Some(clock);
+        session
+    }
+
+    /// Restores a session from its state, measuring its clock, if any, with the given time
+    /// source.
+    ///
+    /// The clock of the player to move restarts, see [`Clock::from_state`]. Returns an error if
+    /// the state is inconsistent.
+    pub fn from_state(state: SessionState, source: S) -> Result<Self, InvalidSession> {
+        let session = Self {
+            game: state.game,
+            result: state.result,
+            draw_offer: state.draw_offer,
+            clock: state.clock.map(|clock| Clock::from_state(clock, source)),
+        };
+        session.validate()?;
+        Ok(session)
+    }
+
+    /// Returns the current state of this session, without the time source of its clock.
+    pub fn state(&self) -> SessionState {
+        SessionState {
+            game: self.game.clone(),
+            result: self.result,
+            draw_offer: self.draw_offer,
+            clock: self.clock.as_ref().map(Clock::state),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Returns the result of the game, or `None` if it's still being played.
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Returns the player who offered a draw that hasn't been answered yet, if any.
+    pub fn draw_offer(&self) -> Option<Player> {
+        self.draw_offer
+    }
+
+    /// Returns the clock of the game, if it's timed.
+    pub fn clock(&self) -> Option<&Clock<S>> {
+        self.clock.as_ref()
+    }
+
+    /// Mark the given tile on behalf of the given player.
+    ///
+    /// Making a play declines a draw offered by the other player. If the play decides the game,
+    /// the session ends with [`Termination::Board`]. Otherwise, the clock is pressed, and if the
+    /// player has run out of time by then, the session ends with [`Termination::Timeout`].
+    ///
+    /// A player who has run out of time before the play can't make it: the session ends with
+    /// [`Termination::Timeout`] and [`IllegalMove::GameOver`] is returned.
+    pub fn mark_tile_as(&mut self, player: Player, play: Play) -> Result<MoveOutcome, IllegalMove> {
+        if self.is_over() || self.check_flag().is_some() {
+            return Err(IllegalMove::GameOver);
+        }
+        let outcome = self.game.try_mark_tile_as(player, play)?;
+        if self.draw_offer == Some(player.other()) {
+            self.draw_offer = None;
+        }
+        if let MoveOutcome::GameDecided(outcome) = outcome {
+            self.end(outcome, Termination::Board);
+        } else if let Some(Err(FlagFall { player })) = self.clock.as_mut().map(Clock::press) {
+            let outcome = self.game.timeout_outcome(player);
+            self.end(outcome, Termination::Timeout);
+        }
+        Ok(outcome)
+    }
+
+    /// Ends the game if a player has run out of time according to the clock, and returns its
+    /// result if so.
+    ///
+    /// See [`GameSession::time_out`].
+    pub fn check_flag(&mut self) -> Option<GameResult> {
+        let player = self.clock.as_ref()?.flagged_player()?;
+        self.time_out(player).ok()
+    }
+
+    /// Ends the game with the given player resigning, which makes the other player win.
+    pub fn resign(&mut self, player: Player) -> Result<GameResult, SessionError> {
+        self.check_in_progress()?;
+        Ok(self.end(
+            BoardOutcome::WonBy(player.other()),
+            Termination::Resignation,
+        ))
+    }
+
+    /// Ends the game with the given player leaving it, which makes the other player win.
+    pub fn abandon(&mut self, player: Player) -> Result<GameResult, SessionError> {
+        self.check_in_progress()?;
+        Ok(self.end(
+            BoardOutcome::WonBy(player.other()),
+            Termination::Abandonment,
+        ))
+    }
+
+    /// Ends the game with the given player running out of time.
+    ///
+    /// See [`Game::timeout_outcome`] for who wins.
+    pub fn time_out(&mut self, player: Player) -> Result<GameResult, SessionError> {
+        self.check_in_progress()?;
+        let outcome = self.game.timeout_outcome(player);
+        Ok(self.end(outcome, Termination::Timeout))
+    }
+
+    /// Offers a draw to the other player on behalf of the given player.
+    ///
+    /// If the other player has already offered a draw, this accepts it instead.
+    pub fn offer_draw(&mut self, player: Player) -> Result<Option<GameResult>, SessionError> {
+        self.check_in_progress()?;
+        if self.draw_offer == Some(player.other()) {
+            return self.accept_draw(player).map(Some);
+        }
+        self.draw_offer = Some(player);
+        Ok(None)
+    }
+
+    /// Accepts the draw offered by the other player on behalf of the given player.
+    pub fn accept_draw(&mut self, player: Player) -> Result<GameResult, SessionError> {
+        self.check_in_progress()?;
+        if self.draw_offer != Some(player.other()) {
+            return Err(SessionError::NoDrawOffer);
+        }
+        Ok(self.end(BoardOutcome::Draw, Termination::Agreement))
+    }
+
+    /// Declines the draw offered by the other player on behalf of the given player.
+    pub fn decline_draw(&mut self, player: Player) -> Result<(), SessionError> {
+        self.check_in_progress()?;
+        if self.draw_offer != Some(player.other()) {
+            return Err(SessionError::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    fn check_in_progress(&self) -> Result<(), SessionError> {
+        match self.result {
+            Some(_) => Err(SessionError::GameOver),
+            None => Ok(()),
+        }
+    }
+
+    fn end(&mut self, outcome: BoardOutcome, termination: Termination) -> GameResult {
+        let result = GameResult {
+            outcome,
+            termination,
+        };
+        self.result = Some(result);
+        self.draw_offer = None;
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+        result
+    }
+
+    /// Check that the result, the draw offer and the clock of this session match its game.
+    fn validate(&self) -> Result<(), InvalidSession> {
+        let is_result_valid = match (self.result, self.game.state) {
+            (None, state) => state == BoardState::InProgress,
+            (Some(result), state) if result.termination == Termination::Board => {
+                state == BoardState::Complete(result.outcome)
+            }
+            (Some(_), state) => state == BoardState::InProgress,
+        };
+        if !is_result_valid {
+            return Err(InvalidSession::InvalidResult);
+        }
+        if self.is_over() && self.draw_offer.is_some() {
+            return Err(InvalidSession::DrawOfferAfterGameOver);
+        }
+        let running_player = self.clock.as_ref().and_then(Clock::running_player);
+        if running_player.is_some_and(|player| self.is_over() || player != self.game.current_player)
+        {
+            return Err(InvalidSession::InvalidRunningClock);
+        }
+        Ok(())
+    }
+}
+
+impl<S> From<Game> for GameSession<S> {
+    /// Starts an untimed session from the given game, which is already over if the game has been
+    /// decided.
+    fn from(game: Game) -> Self {
+        let result = match game.state {
+            BoardState::InProgress => None,
+            BoardState::Complete(outcome) => Some(GameResult {
+                outcome,
+                termination: Termination::Board,
+            }),
+        };
+        Self {
+            game,
+            result,
+            draw_offer: None,
+            clock: None,
+        }
+    }
+}
+
+/// The state of a [`GameSession`] at some point in time, without the time source of its clock.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionState {
+    pub game: Game,
+    pub result: Option<GameResult>,
+    /// The player who offered a draw that hasn't been answered yet.
+    pub draw_offer: Option<Player>,
+    #[serde(default)]
+    pub clock: Option<ClockState>,
+}
+
+impl<S: TimeSource + Clone> From<GameSession<S>> for SessionState {
+    fn from(session: GameSession<S>) -> Self {
+        session.state()
+    }
+}
+
+impl<'de> Deserialize<'de> for GameSession {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let state = SessionState::deserialize(deserializer)?;
+        Self::from_state(state, Untimed).map_err(de::Error::custom)
+    }
+}
+
+/// The reason a deserialized [`GameSession`] is inconsistent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidSession {
+    /// The result doesn't match the state of the game: a game decided on the board must have
+    /// that outcome as its result, and a game ended off the board must still be in progress on
+    /// it.
+    InvalidResult,
+    /// A draw offer is pending although the game is over.
+    DrawOfferAfterGameOver,
+    /// The running clock isn't the one of the player to move, or runs after the game is over.
+    InvalidRunningClock,
+}
+
+impl Display for InvalidSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidResult => write!(f, "the result doesn't match the state of the game"),
+            Self::DrawOfferAfterGameOver => {
+                write!(f, "a draw offer is pending although the game is over")
+            }
+            Self::InvalidRunningClock => {
+                write!(f, "the running clock isn't the one of the player to move")
+            }
+        }
+    }
+}
+
+impl Error for InvalidSession {}
+
+/// The reason an action can't be taken in a [`GameSession`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionError {
+    /// The game is already over.
+    GameOver,
+    /// The other player hasn't offered a draw.
+    NoDrawOffer,
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GameOver => write!(f, "the game is already over"),
+            Self::NoDrawOffer => write!(f, "no draw has been offered"),
+        }
+    }
+}
+
+impl Error for SessionError {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        testing::{advance, all_plays, fake_clock, random_positions},
+        TimeControl,
+    };
+
+    /// Returns a game played to the end.
+    fn complete_game() -> Game {
+        let game = random_positions(Default::default(), 0).pop().unwrap();
+        assert_ne!(game.state, BoardState::InProgress);
+        game
+    }
+
+    /// Returns the error of the session with the given state.
+    fn session_error(
+        game: Game,
+        result: Option<GameResult>,
+        draw_offer: Option<Player>,
+    ) -> Result<(), InvalidSession> {
+        let state = SessionState {
+            game,
+            result,
+            draw_offer,
+            clock: None,
+        };
+        GameSession::from_state(state, Untimed).map(drop)
+    }
+
+    #[test]
+    fn resign_and_draw() {
+        let mut session = GameSession::new();
+        assert_eq!(session.offer_draw(Player::Circle), Ok(None));
+        assert_eq!(
+            session.accept_draw(Player::Circle),
+            Err(SessionError::NoDrawOffer)
+        );
+        assert_eq!(session.decline_draw(Player::Cross), Ok(()));
+        assert_eq!(session.draw_offer(), None);
+
+        let play = all_plays().next().unwrap();
+        assert_eq!(session.offer_draw(Player::Cross), Ok(None));
+        assert!(session.mark_tile_as(Player::Circle, play).is_ok());
+        assert_eq!(session.draw_offer(), None);
+
+        let result = session.resign(Player::Cross).unwrap();
+        assert_eq!(
+            result,
+            GameResult {
+                outcome: BoardOutcome::WonBy(Player::Circle),
+                termination: Termination::Resignation,
+            }
+        );
+        assert_eq!(session.result(), Some(result));
+        assert_eq!(
+            session.offer_draw(Player::Cross),
+            Err(SessionError::GameOver)
+        );
+        assert_eq!(
+            session.mark_tile_as(Player::Cross, play),
+            Err(IllegalMove::GameOver)
+        );
+    }
+
+    #[test]
+    fn timeout() {
+        let (clock, time) = fake_clock(TimeControl::SuddenDeath {
+            total: Duration::from_secs(60),
+        });
+        let mut session = GameSession::with_clock(clock);
+        let mut plays = all_plays();
+        let play = plays.next().unwrap();
+        assert_eq!(
+            session.clock().unwrap().running_player(),
+            Some(Player::Circle)
+        );
+        advance(&time, 10);
+        assert!(session.mark_tile_as(Player::Circle, play).is_ok());
+        assert_eq!(
+            session.clock().unwrap().running_player(),
+            Some(Player::Cross)
+        );
+        assert_eq!(session.check_flag(), None);
+
+        advance(&time, 61);
+        let play = session.game().legal_moves().next().unwrap();
+        assert_eq!(
+            session.mark_tile_as(Player::Cross, play),
+            Err(IllegalMove::GameOver)
+        );
+        let result = session.result().unwrap();
+        assert_eq!(result.termination, Termination::Timeout);
+        assert_eq!(
+            result.outcome,
+            session.game().timeout_outcome(Player::Cross)
+        );
+        assert_eq!(session.clock().unwrap().running_player(), None);
+        assert_eq!(session.check_flag(), None);
+    }
+
+    #[test]
+    fn from_complete_game() {
+        let game = complete_game();
+        let BoardState::Complete(outcome) = game.state else {
+            unreachable!()
+        };
+        let session = GameSession::<Untimed>::from(game);
+        assert_eq!(
+            session.result(),
+            Some(GameResult {
+                outcome,
+                termination: Termination::Board,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_result() {
+        let game = complete_game();
+        let BoardState::Complete(outcome) = game.state else {
+            unreachable!()
+        };
+        let board_result = GameResult {
+            outcome,
+            termination: Termination::Board,
+        };
+        let resignation = GameResult {
+            outcome: BoardOutcome::WonBy(Player::Circle),
+            termination: Termination::Resignation,
+        };
+        let wrong_outcome = GameResult {
+            outcome: match outcome {
+                BoardOutcome::Draw => BoardOutcome::WonBy(Player::Circle),
+                BoardOutcome::WonBy(_) => BoardOutcome::Draw,
+            },
+            ..board_result
+        };
+
+        assert_eq!(
+            session_error(game.clone(), Some(board_result), None),
+            Ok(())
+        );
+        assert_eq!(session_error(Game::new(), Some(resignation), None), Ok(()));
+        for (game, result) in [
+            (game.clone(), None),
+            (game.clone(), Some(resignation)),
+            (game, Some(wrong_outcome)),
+            (Game::new(), Some(board_result)),
+        ] {
+            assert_eq!(
+                session_error(game, result, None),
+                Err(InvalidSession::InvalidResult)
+            );
+        }
+    }
+
+    #[test]
+    fn draw_offer_after_game_over() {
+        let resignation = GameResult {
+            outcome: BoardOutcome::WonBy(Player::Circle),
+            termination: Termination::Resignation,
+        };
+        assert_eq!(
+            session_error(Game::new(), None, Some(Player::Cross)),
+            Ok(())
+        );
+        assert_eq!(
+            session_error(Game::new(), Some(resignation), Some(Player::Cross)),
+            Err(InvalidSession::DrawOfferAfterGameOver)
+        );
+    }
+
+    #[test]
+    fn invalid_running_clock() {
+        let session_error = |running_player, result| {
+            let (mut clock, _) = fake_clock(TimeControl::SuddenDeath {
+                total: Duration::from_secs(60),
+            });
+            if let Some(player) = running_player {
+                clock.start(player);
+            }
+            let state = SessionState {
+                game: Game::new(),
+                result,
+                draw_offer: None,
+                clock: Some(clock.state()),
+            };
+            GameSession::from_state(state, Untimed).map(drop)
+        };
+        let resignation = GameResult {
+            outcome: BoardOutcome::WonBy(Player::Circle),
+            termination: Termination::Resignation,
+        };
+
+        assert_eq!(session_error(None, None), Ok(()));
+        assert_eq!(session_error(Some(Player::Circle), None), Ok(()));
+        assert_eq!(session_error(None, Some(resignation)), Ok(()));
+        assert_eq!(
+            session_error(Some(Player::Cross), None),
+            Err(InvalidSession::InvalidRunningClock)
+        );
+        assert_eq!(
+            session_error(Some(Player::Circle), Some(resignation)),
+            Err(InvalidSession::InvalidRunningClock)
+        );
+    }
+
+    #[test]
+    fn with_game_and_clock() {
+        let time_control = TimeControl::SuddenDeath {
+            total: Duration::from_secs(60),
+        };
+        let mut game = Game::new();
+        game.mark_tile(all_plays().next().unwrap());
+        let (clock, _) = fake_clock(time_control);
+        let session = GameSession::with_game_and_clock(game.clone(), clock);
+        assert_eq!(session.game(), &game);
+        assert_eq!(
+            session.clock().unwrap().running_player(),
+            Some(game.current_player)
+        );
+
+        let (clock, _) = fake_clock(time_control);
+        let session = GameSession::with_game_and_clock(complete_game(), clock);
+        assert!(session.is_over());
+        assert_eq!(session.clock().unwrap().running_player(), None);
+    }
+
+    #[test]
+    fn timed_state_round_trip() {
+        let (clock, time) = fake_clock(TimeControl::SuddenDeath {
+            total: Duration::from_secs(60),
+        });
+        let mut session = GameSession::with_clock(clock);
+        advance(&time, 10);
+        let play = all_plays().next().unwrap();
+        assert!(session.mark_tile_as(Player::Circle, play).is_ok());
+        session.offer_draw(Player::Circle).unwrap();
+        let state = session.state();
+
+        // The running clock of Cross restarts when loading, so the time source can be any
+        // instant, such as the current one.
+        let loaded = GameSession::from_state(state, Instant::now()).unwrap();
+        assert_eq!(loaded.game(), session.game());
+        assert_eq!(loaded.draw_offer(), Some(Player::Circle));
+        let clock = loaded.clock().unwrap();
+        assert_eq!(clock.running_player(), Some(Player::Cross));
+        assert_eq!(clock.remaining(Player::Circle), Duration::from_secs(50));
+        assert!(clock.remaining(Player::Cross) <= Duration::from_secs(60));
+    }
+}
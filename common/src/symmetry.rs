@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Board, BoardIndex, Game, Play, Region, Tile};
+
+/// One of the 8 symmetries of a square board: the rotations and reflections of the dihedral
+/// group D4.
+///
+/// A symmetry of a [`Game`] is applied to the layout of the regions and to the layout of the
+/// tiles within every region at the same time, so every rule of the game is preserved: the
+/// transformed game is played exactly like the original one with the plays transformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Symmetry {
+    Identity,
+    /// A quarter turn clockwise.
+    Rotate90,
+    /// A half turn.
+    Rotate180,
+    /// A quarter turn counterclockwise.
+    Rotate270,
+    /// A reflection across the vertical axis, swapping left and right.
+    FlipHorizontal,
+    /// A reflection across the horizontal axis, swapping up and down.
+    FlipVertical,
+    /// A reflection across the diagonal from the upper left to the lower right.
+    Transpose,
+    /// A reflection across the diagonal from the upper right to the lower left.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Self::Identity,
+        Self::Rotate90,
+        Self::Rotate180,
+        Self::Rotate270,
+        Self::FlipHorizontal,
+        Self::FlipVertical,
+        Self::Transpose,
+        Self::AntiTranspose,
+    ];
+
+    /// Returns the symmetry that undoes this one.
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Rotate90 => Self::Rotate270,
+            Self::Rotate270 => Self::Rotate90,
+            symmetry => symmetry,
+        }
+    }
+
+    /// Returns the symmetry equivalent to applying this one, then `other`.
+    pub fn then(self, other: Self) -> Self {
+        let apply_both = |index: BoardIndex| index.transform(self).transform(other);
+        // Two indices that aren't on the same line through the center are enough to tell all the
+        // symmetries apart.
+        Self::ALL
+            .into_iter()
+            .find(|&symmetry| {
+                [BoardIndex::UpperLeft, BoardIndex::Up]
+                    .into_iter()
+                    .all(|index| index.transform(symmetry) == apply_both(index))
+            })
+            .expect("the symmetries of a square should always be closed under composition.")
+    }
+
+    /// Returns the play at the position the given play is moved to by this symmetry.
+    pub fn transform_play(self, (region_index, tile_index): Play) -> Play {
+        (region_index.transform(self), tile_index.transform(self))
+    }
+}
+
+impl BoardIndex {
+    /// Returns the index at the position this index is moved to by the given symmetry.
+    pub fn transform(self, symmetry: Symmetry) -> Self {
+        let (row, column) = (self.row(), self.column());
+        let (row, column) = match symmetry {
+            Symmetry::Identity => (row, column),
+            Symmetry::Rotate90 => (column, 2 - row),
+            Symmetry::Rotate180 => (2 - row, 2 - column),
+            Symmetry::Rotate270 => (2 - column, row),
+            Symmetry::FlipHorizontal => (row, 2 - column),
+            Symmetry::FlipVertical => (2 - row, column),
+            Symmetry::Transpose => (column, row),
+            Symmetry::AntiTranspose => (2 - column, 2 - row),
+        };
+        Self::from_row_column(row, column)
+            .expect("a symmetry should always move an index within the board.")
+    }
+}
+
+impl<T: Clone> Board<T> {
+    /// Returns a copy of this board with every item moved by the given symmetry.
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        let mut board = self.clone();
+        for (index, item) in self.enumerate() {
+            board[index.transform(symmetry)] = item.clone();
+        }
+        board
+    }
+}
+
+impl Game {
+    /// Returns a copy of this game with the regions, the tiles within every region and the
+    /// previous play index moved by the given symmetry.
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        let mut board = self.board.transform(symmetry);
+        for region in &mut board.tiles {
+            region.board = region.board.transform(symmetry);
+        }
+
+        let mut game = Self {
+            board,
+            state: self.state,
+            current_player: self.current_player,
+            previous_play_index: self
+                .previous_play_index
+                .map(|index| index.transform(symmetry)),
+            rules: self.rules,
+            key: 0,
+        };
+        game.update_key();
+        game
+    }
+
+    /// Returns the canonical form of this game, which is the same for all 8 symmetric games, and
+    /// the symmetry that maps the canonical form back to this game.
+    ///
+    /// Valid plays in the canonical form can be mapped back to plays in this game with
+    /// [`Symmetry::transform_play`].
+    pub fn canonical(&self) -> (Self, Symmetry) {
+        let (symmetry, _) = Symmetry::ALL
+            .into_iter()
+            .map(|symmetry| (symmetry, self.symmetry_key(symmetry)))
+            .min_by(|(_, key0), (_, key1)| key0.cmp(key1))
+            .expect("there should always be at least one symmetry.");
+        (self.transform(symmetry), symmetry.inverse())
+    }
+
+    /// Returns a key of the game transformed by the given symmetry that only depends on the
+    /// tiles and the previous play index, which determine the rest of the game state.
+    fn symmetry_key(&self, symmetry: Symmetry) -> ([u8; 81], Option<usize>) {
+        let mut tiles = [0; 81];
+        for (region_index, Region { board, .. }) in self.board.enumerate() {
+            let region = 9 * usize::from(region_index.transform(symmetry));
+            for (tile_index, tile) in board.enumerate() {
+                tiles[region + usize::from(tile_index.transform(symmetry))] = match tile {
+                    Tile::Unmarked => 0,
+                    Tile::Marked(player) => 1 + *player as u8,
+                };
+            }
+        }
+        let previous_play_index = self
+            .previous_play_index
+            .map(|index| usize::from(index.transform(symmetry)));
+        (tiles, previous_play_index)
+    }
+}
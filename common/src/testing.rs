@@ -0,0 +1,86 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+
+use crate::{BoardIndex, Clock, Game, GameMode, Play, RuleSet, TimeControl, TimeSource};
+
+/// Returns every index of a board, in `usize::from(BoardIndex)` order.
+pub(crate) fn all_indices() -> impl Iterator<Item = BoardIndex> {
+    (0..BoardIndex::N).map(|i| BoardIndex::try_from(i).unwrap())
+}
+
+/// Returns all 81 plays, in region then tile order.
+pub(crate) fn all_plays() -> impl Iterator<Item = Play> {
+    all_indices()
+        .flat_map(|region_index| all_indices().map(move |tile_index| (region_index, tile_index)))
+}
+
+/// Returns the plays of a game played to the end with random valid plays, chosen with the given
+/// seed.
+pub(crate) fn random_plays(rules: RuleSet, seed: u64) -> Vec<Play> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = Game::with_rules(rules);
+    let mut plays = Vec::new();
+    while let Some(play) = game.legal_moves().choose(&mut rng) {
+        game.try_mark_tile(play).unwrap();
+        plays.push(play);
+    }
+    plays
+}
+
+/// Returns every position of a game played with [`random_plays`], from the empty board to the
+/// end of the game.
+pub(crate) fn random_positions(rules: RuleSet, seed: u64) -> Vec<Game> {
+    let mut game = Game::with_rules(rules);
+    let mut positions = vec![game.clone()];
+    for play in random_plays(rules, seed) {
+        game.try_mark_tile(play).unwrap();
+        positions.push(game.clone());
+    }
+    positions
+}
+
+/// Returns the default rules, followed by each rule changed on its own.
+pub(crate) fn rule_sets() -> Vec<RuleSet> {
+    let default = RuleSet::default();
+    vec![
+        default,
+        RuleSet {
+            drawn_regions_count_for_both: true,
+            ..default
+        },
+        RuleSet {
+            won_regions_playable: true,
+            ..default
+        },
+        RuleSet {
+            region_count_tiebreak: true,
+            ..default
+        },
+        RuleSet {
+            no_center_first_move: true,
+            ..default
+        },
+        RuleSet {
+            mode: GameMode::Misere,
+            ..default
+        },
+    ]
+}
+
+/// Returns a clock with a fake time source, and the time it reads.
+pub(crate) fn fake_clock(
+    time_control: TimeControl,
+) -> (Clock<impl TimeSource>, Rc<Cell<Duration>>) {
+    let time = Rc::new(Cell::new(Duration::ZERO));
+    let source = {
+        let time = Rc::clone(&time);
+        move || time.get()
+    };
+    (Clock::new(time_control, source), time)
+}
+
+/// Advances the time read by a fake time source by the given number of seconds.
+pub(crate) fn advance(time: &Cell<Duration>, seconds: u64) {
+    time.set(time.get() + Duration::from_secs(seconds));
+}
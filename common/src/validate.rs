@@ -0,0 +1,334 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::{BoardIndex, BoardOutcome, BoardState, Game, Play, Player, Region, Tile};
+
+impl Game {
+    /// Check that this game is consistent, which is always the case for games made only through
+    /// [`Game::try_mark_tile`] and [`Game::unmark_tile`], but not necessarily for games built or
+    /// changed through the public fields.
+    ///
+    /// A game is consistent when:
+    ///
+    /// - the state of every region matches its tiles according to [`Region::validate`], and only a
+    ///   region that stays playable after being won under
+    ///   [`RuleSet::won_regions_playable`](crate::RuleSet::won_regions_playable) may have a
+    ///   three-in-a-line of both players,
+    /// - a won region that isn't playable has a mark of its winner that completed all of the
+    ///   winner's lines in it, since no play can be made in it afterwards,
+    /// - at most one player has three won regions in a line,
+    /// - the state of the game matches its regions under its rules,
+    /// - Circle has made as many plays as Cross or one more, and it's Cross's turn exactly when
+    ///   Circle is ahead,
+    /// - there's a previous play index if and only if a play has been made, and the player who
+    ///   made the last play has marked a tile with that index in some region,
+    /// - if the game is over, one of those tiles is a play that ended it, since no play can be
+    ///   made afterwards.
+    ///
+    /// Returns the first invariant that doesn't hold otherwise.
+    pub fn validate(&self) -> Result<(), InvalidGame> {
+        let (mut n_circle, mut n_cross) = (0, 0);
+        for (region_index, region) in self.board.enumerate() {
+            if region.has_lines_of_both() && !self.rules.won_regions_playable {
+                return Err(InvalidGame::RegionWonByBoth(region_index));
+            }
+            if region.validate().is_err() {
+                return Err(InvalidGame::InvalidRegionState(region_index));
+            }
+            if let BoardState::Complete(BoardOutcome::WonBy(winner)) = region.state {
+                if !self.rules.won_regions_playable && !region.is_decided_by_one_mark(winner) {
+                    return Err(InvalidGame::MarksAfterRegionDecided(region_index));
+                }
+            }
+            for tile in region.board.tiles {
+                match tile {
+                    Tile::Marked(Player::Circle) => n_circle += 1,
+                    Tile::Marked(Player::Cross) => n_cross += 1,
+                    Tile::Unmarked => (),
+                }
+            }
+        }
+
+        if self.board.check_player(Player::Circle) && self.board.check_player(Player::Cross) {
+            return Err(InvalidGame::GameWonByBoth);
+        }
+
+        // Circle always goes first, so it's either one play ahead of Cross or they're even.
+        let expected_player = match n_circle - n_cross {
+            0 => Player::Circle,
+            1 => Player::Cross,
+            _ => return Err(InvalidGame::InvalidMarkCounts { n_circle, n_cross }),
+        };
+        if self.current_player != expected_player {
+            return Err(InvalidGame::InvalidMarkCounts { n_circle, n_cross });
+        }
+
+        let last_player = self.current_player.other();
+        if self.state != self.rules.get_state(&self.board, last_player) {
+            return Err(InvalidGame::InvalidGameState);
+        }
+
+        // The previous play must have been made by the other player on a tile with the previous
+        // play index in some region.
+        let is_previous_play_valid = match self.previous_play_index {
+            None => n_circle + n_cross == 0,
+            Some(index) => self
+                .board
+                .tiles
+                .iter()
+                .any(|region| region.board[index] == Tile::Marked(last_player)),
+        };
+        if !is_previous_play_valid {
+            return Err(InvalidGame::InvalidPreviousPlayIndex(
+                self.previous_play_index,
+            ));
+        }
+
+        if let (BoardState::Complete(_), Some(index)) = (self.state, self.previous_play_index) {
+            let is_decided_by_last_play = self.board.enumerate().any(|(region_index, region)| {
+                region.board[index] == Tile::Marked(last_player)
+                    && self.is_in_progress_without((region_index, index))
+            });
+            if !is_decided_by_last_play {
+                return Err(InvalidGame::MarksAfterGameOver);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if this game would be in progress without the mark on the given tile.
+    fn is_in_progress_without(&self, (region_index, tile_index): Play) -> bool {
+        let mut board = self.board;
+        let region = &mut board[region_index];
+        region.board[tile_index] = Tile::Unmarked;
+        if region.board.get_state() == BoardState::InProgress {
+            region.state = BoardState::InProgress;
+        }
+        self.rules.get_state(&board, self.current_player) == BoardState::InProgress
+    }
+}
+
+impl Region {
+    /// Check that the state of this region matches its tiles.
+    ///
+    /// A region won by a player must have a three-in-a-line of that player, which it keeps if more
+    /// plays are made in it afterwards. Any other region must have the state its tiles give it.
+    pub fn validate(&self) -> Result<(), InvalidRegionState> {
+        let is_state_valid = match self.state {
+            BoardState::Complete(BoardOutcome::WonBy(winner)) => self.board.check_player(winner),
+            state => state == self.board.get_state(),
+        };
+        if is_state_valid {
+            Ok(())
+        } else {
+            Err(InvalidRegionState)
+        }
+    }
+
+    /// Check if removing a single mark of the given player leaves them without a three-in-a-line,
+    /// which is the case when the region was decided by that mark and no more plays were made in
+    /// it afterwards.
+    fn is_decided_by_one_mark(&self, player: Player) -> bool {
+        self.board
+            .enumerate()
+            .filter(|&(_, &tile)| tile == Tile::Marked(player))
+            .any(|(index, _)| {
+                let mut board = self.board;
+                board[index] = Tile::Unmarked;
+                !board.check_player(player)
+            })
+    }
+}
+
+/// The reason a [`Game`] is inconsistent, as returned by [`Game::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidGame {
+    /// Both players have a three-in-a-line in the given region.
+    RegionWonByBoth(BoardIndex),
+    /// The state of the given region doesn't match its tiles.
+    InvalidRegionState(BoardIndex),
+    /// Both players have three won regions in a line.
+    GameWonByBoth,
+    /// The number of marks of each player doesn't match the player to move.
+    InvalidMarkCounts { n_circle: i32, n_cross: i32 },
+    /// The state of the game doesn't match its regions.
+    InvalidGameState,
+    /// The given previous play index doesn't match the last play.
+    InvalidPreviousPlayIndex(Option<BoardIndex>),
+    /// The given region has been played in after it was decided.
+    MarksAfterRegionDecided(BoardIndex),
+    /// The game has been played after it ended.
+    MarksAfterGameOver,
+}
+
+impl Display for InvalidGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RegionWonByBoth(index) => write!(f, "region {index} is won by both players"),
+            Self::InvalidRegionState(index) => {
+                write!(f, "the state of region {index} doesn't match its tiles")
+            }
+            Self::GameWonByBoth => write!(f, "the game is won by both players"),
+            Self::InvalidMarkCounts { n_circle, n_cross } => write!(
+                f,
+                "{n_circle} circles and {n_cross} crosses don't match the player to move"
+            ),
+            Self::InvalidGameState => write!(f, "the state of the game doesn't match its regions"),
+            Self::InvalidPreviousPlayIndex(Some(index)) => write!(
+                f,
+                "no tile with the previous play index {index} has been marked by the last player"
+            ),
+            Self::InvalidPreviousPlayIndex(None) => {
+                write!(f, "a play has been made but there's no previous play index")
+            }
+            Self::MarksAfterRegionDecided(index) => {
+                write!(f, "region {index} has been played in after it was decided")
+            }
+            Self::MarksAfterGameOver => write!(f, "the game has been played after it ended"),
+        }
+    }
+}
+
+impl Error for InvalidGame {}
+
+/// The error returned by [`Region::validate`] when the state of a region doesn't match its tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidRegionState;
+
+impl Display for InvalidRegionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the state of the region doesn't match its tiles")
+    }
+}
+
+impl Error for InvalidRegionState {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        position::ParsePositionError,
+        testing::{random_positions, rule_sets},
+    };
+
+    /// Returns the error of the game with the given position string, whose states are recomputed
+    /// from its tiles before it's validated.
+    fn position_error(s: &str) -> InvalidGame {
+        match Game::from_position_string(s) {
+            Err(ParsePositionError::InvalidPosition(error)) => error,
+            result => panic!("{s} should be an invalid position, not {result:?}"),
+        }
+    }
+
+    #[test]
+    fn played_games_are_valid() {
+        for rules in rule_sets() {
+            for seed in 0..10 {
+                for game in random_positions(rules, seed) {
+                    assert_eq!(game.validate(), Ok(()), "{rules:?}\n{game}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn region_won_by_both() {
+        assert_eq!(
+            position_error("ooo6/xxx6/9/9/9/9/9/9/9 o a1"),
+            InvalidGame::RegionWonByBoth(BoardIndex::UpperLeft)
+        );
+    }
+
+    #[test]
+    fn invalid_region_state() {
+        let mut game = Game::new();
+        game.board[BoardIndex::Center].state =
+            BoardState::Complete(BoardOutcome::WonBy(Player::Circle));
+        assert_eq!(
+            game.validate(),
+            Err(InvalidGame::InvalidRegionState(BoardIndex::Center))
+        );
+        assert_eq!(
+            game.board[BoardIndex::Center].validate(),
+            Err(InvalidRegionState)
+        );
+        game.board[BoardIndex::Center].state = BoardState::Complete(BoardOutcome::Draw);
+        assert_eq!(
+            game.validate(),
+            Err(InvalidGame::InvalidRegionState(BoardIndex::Center))
+        );
+    }
+
+    #[test]
+    fn marks_after_region_decided() {
+        assert_eq!(
+            position_error("ooo6/9/ooo6/xx1xx1xx1/9/9/9/9/9 o a1"),
+            InvalidGame::MarksAfterRegionDecided(BoardIndex::UpperLeft)
+        );
+    }
+
+    #[test]
+    fn game_won_by_both() {
+        assert_eq!(
+            position_error("ooooooooo/9/9/9/9/9/xxxxxxxxx/9/9 o a1"),
+            InvalidGame::GameWonByBoth
+        );
+    }
+
+    #[test]
+    fn invalid_mark_counts() {
+        let game = Game {
+            current_player: Player::Cross,
+            ..Game::new()
+        };
+        assert_eq!(
+            game.validate(),
+            Err(InvalidGame::InvalidMarkCounts {
+                n_circle: 0,
+                n_cross: 0
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_game_state() {
+        let game = Game {
+            state: BoardState::Complete(BoardOutcome::Draw),
+            ..Game::new()
+        };
+        assert_eq!(game.validate(), Err(InvalidGame::InvalidGameState));
+    }
+
+    #[test]
+    fn invalid_previous_play_index() {
+        let mut game = Game::new();
+        game.try_mark_tile((BoardIndex::Center, BoardIndex::Up))
+            .unwrap();
+        game.previous_play_index = None;
+        assert_eq!(
+            game.validate(),
+            Err(InvalidGame::InvalidPreviousPlayIndex(None))
+        );
+        game.previous_play_index = Some(BoardIndex::Center);
+        assert_eq!(
+            game.validate(),
+            Err(InvalidGame::InvalidPreviousPlayIndex(Some(
+                BoardIndex::Center
+            )))
+        );
+    }
+
+    #[test]
+    fn marks_after_game_over() {
+        // Circle has won two rows of regions, so the game was over before its last play.
+        assert_eq!(
+            position_error("ooooooooo/9/9/xx1xx1xx1/x1xx1xx1x/1xx1xx1x1/ooooooooo/9/9 x a1"),
+            InvalidGame::MarksAfterGameOver
+        );
+        // With one row of regions, its last play could have ended it.
+        assert!(Game::from_position_string("ooooooooo/9/9/xx1xx1xx1/x1x6/9/9/9/9 x a1").is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{BoardIndex, Game, Play, Player, Tile};
+
+/// The random keys XORed into the key of a game for every marked tile, indexed by player, then
+/// by `9 * region + tile`.
+const TILE_KEYS: [[u64; 81]; 2] = {
+    let mut keys = [[0; 81]; 2];
+    let mut i = 0;
+    while i < 2 * 81 {
+        keys[i / 81][i % 81] = random_key(i as u64);
+        i += 1;
+    }
+    keys
+};
+
+/// The random keys XORed into the key of a game for its previous play index, indexed by
+/// `usize::from(BoardIndex)`.
+const PREVIOUS_PLAY_INDEX_KEYS: [u64; BoardIndex::N] = {
+    let mut keys = [0; BoardIndex::N];
+    let mut i = 0;
+    while i < BoardIndex::N {
+        keys[i] = random_key((2 * 81 + i) as u64);
+        i += 1;
+    }
+    keys
+};
+
+/// The random key XORed into the key of a game when it's [`Player::Cross`]'s turn.
+const CROSS_TO_MOVE_KEY: u64 = random_key((2 * 81 + BoardIndex::N) as u64);
+
+/// Returns the `n`th number of the SplitMix64 pseudorandom sequence, so every key is fixed at
+/// compile time.
+const fn random_key(n: u64) -> u64 {
+    let mut z = (n + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Returns the key of a tile marked by the given player.
+pub(crate) fn tile_key(player: Player, (region_index, tile_index): Play) -> u64 {
+    let player = match player {
+        Player::Circle => 0,
+        Player::Cross => 1,
+    };
+    TILE_KEYS[player][9 * usize::from(region_index) + usize::from(tile_index)]
+}
+
+/// Returns the key of the given player being the one to move.
+pub(crate) fn player_key(player: Player) -> u64 {
+    match player {
+        Player::Circle => 0,
+        Player::Cross => CROSS_TO_MOVE_KEY,
+    }
+}
+
+/// Returns the key of the given previous play index, which decides the region the next play is
+/// forced into.
+pub(crate) fn previous_play_index_key(previous_play_index: Option<BoardIndex>) -> u64 {
+    previous_play_index.map_or(0, |index| PREVIOUS_PLAY_INDEX_KEYS[usize::from(index)])
+}
+
+impl Game {
+    /// Returns the 64-bit Zobrist key of this game.
+    ///
+    /// The key is made from the marked tiles, the player to move and the previous play index,
+    /// which decides the region the next play is forced into. The states of the regions and the
+    /// game follow from those, so games that are equal have equal keys, and games that differ
+    /// almost always have different keys.
+    ///
+    /// The key is kept up to date by [`Game::try_mark_tile`] and [`Game::unmark_tile`] at the cost
+    /// of a few XORs per play. It's also what [`Hash`] uses, so hashing a game is cheap.
+    ///
+    /// Changes made directly to the public fields of a game aren't tracked. Call
+    /// [`Game::update_key`] after making any.
+    pub fn key(&self) -> u64 {
+        debug_assert_eq!(
+            self.key,
+            self.compute_key(),
+            "the key should be updated after changing the public fields of a game."
+        );
+        self.key
+    }
+
+    /// Recomputes the Zobrist key of this game from scratch.
+    pub fn update_key(&mut self) {
+        self.key = self.compute_key();
+    }
+
+    fn compute_key(&self) -> u64 {
+        let mut key =
+            player_key(self.current_player) ^ previous_play_index_key(self.previous_play_index);
+        for (region_index, region) in self.board.enumerate() {
+            for (tile_index, tile) in region.board.enumerate() {
+                if let Tile::Marked(player) = *tile {
+                    key ^= tile_key(player, (region_index, tile_index));
+                }
+            }
+        }
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        testing::{random_plays, rule_sets},
+        FastGame, Game,
+    };
+
+    #[test]
+    fn incremental_key_matches_computed_key() {
+        for rules in rule_sets() {
+            for seed in 0..10 {
+                let plays = random_plays(rules, seed);
+                let mut game = Game::with_rules(rules);
+                let mut games = vec![game.clone()];
+                for &play in &plays {
+                    game.try_mark_tile(play).unwrap();
+                    assert_eq!(game.key, game.compute_key());
+                    games.push(game.clone());
+                }
+                for (i, &play) in plays.iter().enumerate().rev() {
+                    let previous_play_index = i.checked_sub(1).map(|i| plays[i].1);
+                    assert!(game.unmark_tile(play, previous_play_index));
+                    assert_eq!(game.key, game.compute_key());
+                    assert_eq!(game, games[i]);
+                    assert_eq!(game.key, games[i].key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn converted_key_matches_computed_key() {
+        for seed in 0..10 {
+            let mut game = Game::new();
+            for play in random_plays(game.rules, seed) {
+                game.try_mark_tile(play).unwrap();
+                let converted = Game::from(&FastGame::from(&game));
+                assert_eq!(converted.key, converted.compute_key());
+                assert_eq!(converted, game);
+                assert_eq!(converted.key, game.key);
+            }
+        }
+    }
+}
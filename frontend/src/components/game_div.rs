@@ -1,8 +1,8 @@
-use std::{ops::AddAssign, rc::Rc};
+use std::{cell::RefCell, ops::AddAssign, rc::Rc};
 
 use crate::components::RegionDiv;
 use common::{
-    ai::mct::{make_move, Node},
+    ai::{Agent, MctsAgent, SearchLimits},
     BoardIndex, BoardOutcome, BoardState, Game, MarkTileResult, Play, Player,
 };
 use gloo_console::log;
@@ -12,32 +12,53 @@ use yew::{platform::spawn_local, prelude::*, virtual_dom::VNode};
 use yew_agent::oneshot::{oneshot, use_oneshot_runner};
 use yew_router::hooks::use_navigator;
 
+type WorkerAgent = MctsAgent<Box<dyn Fn() -> Duration>>;
+
+thread_local! {
+    /// The agent of the worker, kept between tasks so that it can reuse its tree after the user's
+    /// reply.
+    static AGENT: RefCell<WorkerAgent> = RefCell::new({
+        let start = Instant::now();
+        let mut agent: WorkerAgent = MctsAgent::with_time_source(Box::new(move || start.elapsed()));
+        agent.set_tree_reuse(true);
+        agent
+    });
+}
+
 #[oneshot]
 pub fn AITask(game: Game) -> Play {
-    let timeout = Instant::now() + Duration::from_secs_f32(1.0);
-    let should_terminate = move |node: &Node| {
-        if Instant::now() > timeout {
-            log!(format!(
-                "average score: {:.3} over {} simulations",
-                1.0 - (node.score() / node.n_visits() as f32),
-                node.n_visits()
-            ));
-            log!(node
-                .children()
-                .map(|node| format!(
-                    "{:.0} / {} = {:.3}",
-                    node.score(),
-                    node.n_visits(),
-                    node.score() / node.n_visits() as f32
-                ))
-                .collect::<Vec<_>>());
-            true
-        } else {
-            false
-        }
-    };
+    AGENT.with_borrow_mut(|agent| choose_move(agent, &game))
+}
 
-    make_move(game, should_terminate)
+fn choose_move(agent: &mut WorkerAgent, game: &Game) -> Play {
+    let play = agent.choose_move(game, &SearchLimits::time(Duration::from_secs_f32(1.0)));
+
+    if let Some(info) = agent.search_info() {
+        let (score, n_visits) = info.plays.iter().fold((0.0, 0), |(score, n_visits), play| {
+            (
+                score + play.average_score * play.n_visits as f32,
+                n_visits + play.n_visits,
+            )
+        });
+        log!(format!(
+            "average score: {:.3} over {} simulations with seed {:?}",
+            score / n_visits as f32,
+            info.iterations,
+            info.seed
+        ));
+        log!(info
+            .plays
+            .iter()
+            .map(|play| format!(
+                "{:.0} / {} = {:.3}",
+                play.average_score * play.n_visits as f32,
+                play.n_visits,
+                play.average_score
+            ))
+            .collect::<Vec<_>>());
+    }
+
+    play
 }
 
 #[function_component(AIGameDiv)]
@@ -60,12 +81,9 @@ pub(crate) fn ai_game_div() -> Html {
         Callback::from(move |play| {
             let mut game = (*state).clone();
 
-            // short-circuit if it's the AI's turn.
-            if game.current_player != *player.borrow() {
-                return;
-            }
-
-            if matches!(game.mark_tile(play), MarkTileResult::NoChange) {
+            // short-circuit if it's the AI's turn or the play is invalid.
+            if let Err(error) = game.try_mark_tile_as(*player.borrow(), play) {
+                log!(format!("{}", error));
                 return;
             }
             state.set(game.clone());
@@ -212,7 +230,7 @@ fn render_game_div(
             let callback = game.is_region_enabled(index).then(|| callback.clone());
 
             html! {
-                <RegionDiv index={index} region={region} callback={callback} />
+                <RegionDiv index={index} region={region} rules={game.rules} callback={callback} />
             }
         })
         .collect();
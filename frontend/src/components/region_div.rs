@@ -1,11 +1,12 @@
 use crate::components::{player_svg, TileDiv};
-use common::{BoardIndex, BoardItem, BoardState, Region};
+use common::{BoardIndex, BoardState, Region, RuleSet};
 use yew::prelude::*;
 
 #[derive(Clone, PartialEq, Properties)]
 pub(crate) struct Props {
     pub(crate) index: BoardIndex,
     pub(crate) region: Region,
+    pub(crate) rules: RuleSet,
     pub(crate) callback: Option<Callback<(BoardIndex, BoardIndex), ()>>,
 }
 
@@ -16,7 +17,7 @@ pub(crate) fn region_div(props: &Props) -> Html {
         .board
         .enumerate()
         .map(|(tile_index, &tile)| {
-            let onclick = if props.region.is_tile_enabled(tile_index) {
+            let onclick = if props.region.is_tile_enabled(tile_index, &props.rules) {
                 props.callback.clone().map(|callback| {
                     let region_index = props.index;
                     Callback::from(move |_| callback.emit((region_index, tile_index)))
@@ -31,20 +32,19 @@ pub(crate) fn region_div(props: &Props) -> Html {
         })
         .collect();
 
-    let inner =
-        if let BoardState::Complete(common::BoardOutcome::WonBy(winner)) = props.region.state {
-            player_svg(winner)
-        } else {
-            html! {
-                <div class="grid grid-cols-3 grid-rows-3 aspect-square gap-0.5 bg-white">
-                    { children }
-                </div>
-            }
-        };
+    let is_open = props.rules.is_region_open(&props.region);
+    let inner = match props.region.state {
+        BoardState::Complete(common::BoardOutcome::WonBy(winner)) if !is_open => player_svg(winner),
+        _ => html! {
+            <div class="grid grid-cols-3 grid-rows-3 aspect-square gap-0.5 bg-white">
+                { children }
+            </div>
+        },
+    };
 
     let css = classes!(
         "p-3",
-        if props.callback.is_some() && props.region.is_markable() {
+        if props.callback.is_some() && is_open {
             "bg-base"
         } else {
             "bg-fore"
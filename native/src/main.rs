@@ -1,9 +1,14 @@
 // use tracing_subscriber::{filter::LevelFilter, fmt::format::FmtSpan, prelude::*, Registry};
 
-use std::time::{Duration, Instant};
+use std::{
+    env,
+    num::NonZeroUsize,
+    process,
+    time::{Duration, Instant},
+};
 
 use common::{
-    ai::{self},
+    ai::{Agent, MctsAgent, SearchLimits},
     BoardIndex, Game,
 };
 
@@ -18,31 +23,40 @@ fn main() {
     // let play = ai::mct::make_move(game.clone(), |node| node.n_visits() > 200);
     // game.mark_tile(play);
 
+    let n_threads = match env::args().nth(1).map(|arg| arg.parse::<NonZeroUsize>()) {
+        None => 1,
+        Some(Ok(n_threads)) => n_threads.get(),
+        Some(Err(_)) => {
+            eprintln!("usage: native [number of threads to search on, at least 1]");
+            process::exit(2);
+        }
+    };
+
     let mut total_time = Duration::ZERO;
     let mut game = Game::new();
     game.mark_tile((BoardIndex::Center, BoardIndex::Up));
     let mut games = vec![game; 10];
 
+    let mut agent = MctsAgent::new();
+    agent.set_threads(n_threads);
+    let limits = SearchLimits::iterations(20000);
     for game in games.iter_mut() {
         let start_time = Instant::now();
-        let play = ai::mct::make_move(game.clone(), |node| {
-            if node.n_visits() > 20000 {
-                println!("score average: {}", node.average_score());
-                println!(
-                    "{:?}",
-                    node.children()
-                        .map(|child| child.average_score())
-                        .collect::<Vec<_>>()
-                );
-                true
-            } else {
-                false
-            }
-        });
-        let end_time = Instant::now();
-        let diff = end_time - start_time;
+        let play = agent.choose_move(game, &limits);
+        let diff = start_time.elapsed();
         total_time += diff;
-        println!("{:?}", diff);
+
+        let info = agent
+            .search_info()
+            .expect("the agent should keep information about its last search.");
+        let scores = info
+            .plays
+            .iter()
+            .map(|play| play.average_score)
+            .collect::<Vec<_>>();
+        println!("iterations: {}", info.iterations);
+        println!("{scores:?}");
+        println!("{diff:?}");
         game.mark_tile(play);
     }
 